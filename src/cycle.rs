@@ -0,0 +1,194 @@
+use super::prelude::*;
+use std::hash::Hash;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    // Remembers every key seen, finds the cycle in a single pass
+    Hash,
+    // Tortoise and hare, constant memory
+    #[allow(dead_code)]
+    Floyd,
+    // Power-of-two tortoise teleports, constant memory and fewer steps than Floyd
+    Brent,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    // Earliest generation whose normalised state is the same as generation `n`
+    pub fn equivalent(&self, n: usize) -> usize {
+        if n < self.start { n } else { self.start + (n - self.start) % self.period }
+    }
+
+    // Number of whole periods between `equivalent(n)` and `n`
+    pub fn periods_skipped(&self, n: usize) -> usize {
+        n.saturating_sub(self.start) / self.period
+    }
+
+    pub fn state_at<S: Clone>(&self, n: usize, initial: &S, mut step: impl FnMut(&S) -> S) -> S {
+        (0..self.equivalent(n)).fold(initial.clone(), |state, _| step(&state))
+    }
+
+    // Like `state_at`, for states normalised up to a translation: `offset` extracts the
+    // translation and `shift` moves a state by the drift accumulated in the skipped periods.
+    pub fn translated_state_at<S: Clone>(
+        &self,
+        n: usize,
+        initial: &S,
+        mut step: impl FnMut(&S) -> S,
+        offset: impl Fn(&S) -> isize,
+        shift: impl FnOnce(&mut S, isize),
+    ) -> S {
+        let target = self.equivalent(n);
+        let mut state = initial.clone();
+        let mut at_target = None;
+        let mut start_offset = 0;
+        for generation in 0..self.start + self.period {
+            if generation == self.start { start_offset = offset(&state); }
+            if generation == target { at_target = Some(state.clone()); }
+            state = step(&state);
+        }
+
+        let drift = offset(&state) - start_offset;
+        let mut at_target = at_target.expect("Target generation is always inside the cycle");
+        shift(&mut at_target, drift * self.periods_skipped(n) as isize);
+        at_target
+    }
+}
+
+// Finds the first repetition in the sequence `initial, step(initial), ...` where two states
+// are considered the same if their keys are equal. Never returns if the sequence has no cycle.
+pub fn find_cycle<S: Clone, K: Eq + Hash>(
+    initial: &S,
+    mut step: impl FnMut(&S) -> S,
+    key: impl Fn(&S) -> K,
+    strategy: Strategy,
+) -> Cycle {
+    match strategy {
+        Strategy::Hash => {
+            let mut seen = HashMap::new();
+            let mut state = initial.clone();
+            for generation in 0.. {
+                if let Some(start) = seen.insert(key(&state), generation) {
+                    return Cycle { start, period: generation - start };
+                }
+                state = step(&state);
+            }
+            unreachable!()
+        }
+        Strategy::Floyd => {
+            let mut tortoise = step(initial);
+            let mut hare = step(&tortoise);
+            while key(&tortoise) != key(&hare) {
+                tortoise = step(&tortoise);
+                hare = step(&hare);
+                hare = step(&hare);
+            }
+
+            let mut start = 0;
+            tortoise = initial.clone();
+            while key(&tortoise) != key(&hare) {
+                tortoise = step(&tortoise);
+                hare = step(&hare);
+                start += 1;
+            }
+
+            let mut period = 1;
+            hare = step(&tortoise);
+            while key(&tortoise) != key(&hare) {
+                hare = step(&hare);
+                period += 1;
+            }
+
+            Cycle { start, period }
+        }
+        Strategy::Brent => {
+            let mut power = 1;
+            let mut period = 1;
+            let mut tortoise = initial.clone();
+            let mut hare = step(initial);
+            while key(&tortoise) != key(&hare) {
+                if power == period {
+                    tortoise = hare.clone();
+                    power *= 2;
+                    period = 0;
+                }
+                hare = step(&hare);
+                period += 1;
+            }
+
+            let mut start = 0;
+            tortoise = initial.clone();
+            hare = (0..period).fold(initial.clone(), |state, _| step(&state));
+            while key(&tortoise) != key(&hare) {
+                tortoise = step(&tortoise);
+                hare = step(&hare);
+                start += 1;
+            }
+
+            Cycle { start, period }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_plus_one(x: &u32) -> u32 {
+        (x * x + 1) % 255
+    }
+
+    // The pattern cycles like `square_plus_one`, while the offset moves by a pattern-dependent amount
+    fn drifting((pattern, offset): &(u32, isize)) -> (u32, isize) {
+        (square_plus_one(pattern), offset + (pattern % 3) as isize - 1)
+    }
+
+    #[test]
+    fn strategies_agree() {
+        // 0, 1, 2, 5, 26, 166, 16, 2, ...
+        for &strategy in [Strategy::Hash, Strategy::Floyd, Strategy::Brent].iter() {
+            assert_eq!(find_cycle(&0, square_plus_one, |&x| x, strategy), Cycle { start: 2, period: 6 });
+        }
+    }
+
+    #[test]
+    fn cycle_without_tail() {
+        let step = |x: &u32| (x + 1) % 7;
+        for &strategy in [Strategy::Hash, Strategy::Floyd, Strategy::Brent].iter() {
+            assert_eq!(find_cycle(&3, step, |&x| x, strategy), Cycle { start: 0, period: 7 });
+        }
+    }
+
+    #[test]
+    fn state_at_matches_brute_force() {
+        let cycle = find_cycle(&0, square_plus_one, |&x| x, Strategy::Brent);
+        let mut state = 0;
+        for n in 0..100 {
+            assert_eq!(cycle.state_at(n, &0, square_plus_one), state);
+            state = square_plus_one(&state);
+        }
+    }
+
+    #[test]
+    fn translated_state_at_matches_brute_force() {
+        let initial = (0, 0);
+        let cycle = find_cycle(&initial, drifting, |&(pattern, _)| pattern, Strategy::Hash);
+        let mut state = initial;
+        for n in 0..100 {
+            let translated = cycle.translated_state_at(
+                n,
+                &initial,
+                drifting,
+                |&(_, offset)| offset,
+                |(_, offset), drift| *offset += drift,
+            );
+            assert_eq!(translated, state, "generation {}", n);
+            state = drifting(&state);
+        }
+    }
+}
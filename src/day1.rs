@@ -1,26 +1,245 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use std::io::{self, BufRead};
 type Input = Vec<i32>;
 
+// Streaming version of the frequency calibration: changes can be pushed one at a time,
+// and only the distinct frequencies are kept until the first repeat is found.
+pub struct Calibrator {
+    sum: i64,
+    seen: HashSet<i64>,
+    first_repeat: Option<i64>,
+}
+
+impl Default for Calibrator {
+    fn default() -> Self { Self::new() }
+}
+
+impl Calibrator {
+    pub fn new() -> Self {
+        let mut seen = HashSet::new();
+        seen.insert(0);
+        Self { sum: 0, seen, first_repeat: None }
+    }
+
+    pub fn push(&mut self, change: i64) -> i64 {
+        self.sum += change;
+        if self.first_repeat.is_none() && !self.seen.insert(self.sum) {
+            self.first_repeat = Some(self.sum);
+            self.seen = HashSet::new();
+        }
+        self.sum
+    }
+
+    #[allow(dead_code)]
+    pub fn sum(&self) -> i64 { self.sum }
+
+    // First frequency reached twice among the changes pushed so far
+    #[allow(dead_code)]
+    pub fn first_repeat(&self) -> Option<i64> { self.first_repeat }
+
+    // Reads one change per line, ignoring blank lines
+    #[allow(dead_code)]
+    pub fn read(&mut self, reader: impl BufRead) -> io::Result<()> {
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let change = line.parse().map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("Invalid change {:?} at line {}: {}", line, idx + 1, e))
+            })?;
+            self.push(change);
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut calibrator = Self::new();
+        calibrator.read(reader)?;
+        Ok(calibrator)
+    }
+}
+
+impl Extend<i64> for Calibrator {
+    fn extend<I: IntoIterator<Item = i64>>(&mut self, changes: I) {
+        for change in changes {
+            self.push(change);
+        }
+    }
+}
+
+impl iter::FromIterator<i64> for Calibrator {
+    fn from_iter<I: IntoIterator<Item = i64>>(changes: I) -> Self {
+        let mut calibrator = Self::new();
+        calibrator.extend(changes);
+        calibrator
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Repeat {
+    pub frequency: i64,
+    // Pass over the list of changes, starting from 0
+    pub iteration: usize,
+    // Index of the change that reached the repeated frequency
+    pub index: usize,
+}
+
+// After `i` changes of the first pass the frequency is `freqs[i]`, and after `i` changes
+// of the `m`-th pass it's `freqs[i] + m * drift`. So a frequency can only be repeated by
+// one in the same residue class modulo the drift, and the first to do so is the nearest
+// one in the direction of the drift.
+pub fn first_repeat(changes: &[i32]) -> Option<Repeat> {
+    let n = changes.len();
+    if n == 0 {
+        return None;
+    }
+
+    let freqs = iter::once(0)
+        .chain(changes.iter().scan(0i64, |sum, &change| {
+            *sum += change as i64;
+            Some(*sum)
+        }))
+        .take(n)
+        .collect::<Vec<_>>();
+    let drift = changes.iter().map(|&change| change as i64).sum::<i64>();
+
+    // `time` is the number of changes applied when `frequency` is reached again
+    let repeat_at = |time: usize, frequency: i64| Repeat {
+        frequency,
+        iteration: (time - 1) / n,
+        index: (time - 1) % n,
+    };
+
+    let mut seen = HashSet::with_capacity(n);
+    if let Some((time, &frequency)) = freqs.iter().enumerate().find(|&(_, &f)| !seen.insert(f)) {
+        return Some(repeat_at(time, frequency));
+    }
+    if drift == 0 {
+        return Some(repeat_at(n, 0));
+    }
+
+    let mut groups = HashMap::new();
+    for (i, &f) in freqs.iter().enumerate() {
+        groups.entry(f.rem_euclid(drift)).or_insert_with(Vec::new).push((f, i));
+    }
+
+    groups
+        .values_mut()
+        .flat_map(|group| {
+            group.sort_unstable_by_key(|&(f, _)| f * drift.signum());
+            group
+                .windows(2)
+                .map(|pair| {
+                    let ((from, i), (to, _)) = (pair[0], pair[1]);
+                    (((to - from) / drift) as usize * n + i, to)
+                })
+                .collect::<Vec<_>>()
+        })
+        .min_by_key(|&(time, _)| time)
+        .map(|(time, frequency)| repeat_at(time, frequency))
+}
+
 pub fn input_generator(input: &str) -> Input {
     input.lines().map(|line| line.parse().expect("Invalid input line")).collect()
 }
 
-pub fn part1(input: &Input) -> i32 {
-    input.iter().sum()
+pub fn part1(input: &Input) -> i64 {
+    input.iter().map(|&change| change as i64).sum()
+}
+
+pub fn part2(input: &Input) -> Option<i64> {
+    first_repeat(input).map(|repeat| repeat.frequency)
 }
 
-pub fn part2(input: &Input) -> i32 {
-    let mut sum = 0;
-    let mut seen = HashSet::new();
-    seen.insert(0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
 
-    for &n in input.iter().cycle() {
-        sum += n;
-        if !seen.insert(sum) {
-            break;
+    fn changes(input: &str) -> Input {
+        input.split_whitespace().map(|change| change.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn part2_examples() {
+        assert_eq!(part2(&changes("+1 -1")), Some(0));
+        assert_eq!(part2(&changes("+3 +3 +4 -2 -4")), Some(10));
+        assert_eq!(part2(&changes("-6 +3 +8 +5 -6")), Some(5));
+        assert_eq!(part2(&changes("+7 +7 -2 -7 -4")), Some(14));
+        assert_eq!(part2(&changes("+1 +1")), None);
+        assert_eq!(part2(&changes("")), None);
+    }
+
+    #[test]
+    fn reports_where_the_repeat_happens() {
+        // 0, 3, 6, 10, 8, 4, then 7, 10
+        assert_eq!(first_repeat(&changes("+3 +3 +4 -2 -4")), Some(Repeat { frequency: 10, iteration: 1, index: 1 }));
+        // 0, 1, 0
+        assert_eq!(first_repeat(&changes("+1 -1")), Some(Repeat { frequency: 0, iteration: 0, index: 1 }));
+    }
+
+    #[test]
+    fn repeats_beyond_i32() {
+        let big = i32::MAX;
+        // 0, MAX, 2 MAX, MAX + 1, then 2 MAX - 1, 3 MAX - 1, 2 MAX
+        let changes = [big, big, 1 - big, -2];
+        let repeat = first_repeat(&changes).unwrap();
+        assert_eq!(repeat, Repeat { frequency: 2 * big as i64, iteration: 1, index: 2 });
+
+        let mut calibrator = Calibrator::new();
+        changes.iter().cycle().take(8).for_each(|&change| { calibrator.push(change as i64); });
+        assert_eq!(calibrator.first_repeat(), Some(repeat.frequency));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Lcg::new(1);
+        for _ in 0..200 {
+            let len = rng.below(7) + 1;
+            let changes = (0..len).map(|_| rng.range(-10, 11) as i32).collect::<Vec<_>>();
+            let mut calibrator = Calibrator::new();
+            changes.iter().cycle().take(10_000).for_each(|&change| { calibrator.push(change as i64); });
+            assert_eq!(part2(&changes), calibrator.first_repeat(), "{:?}", changes);
         }
     }
 
-    sum
+    #[test]
+    fn reads_changes_from_a_reader() {
+        let calibrator = Calibrator::from_reader(io::Cursor::new("+3\n\n  -1 \n+10\n")).unwrap();
+        assert_eq!(calibrator.sum(), 12);
+        assert_eq!(calibrator.first_repeat(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        let mut calibrator = Calibrator::new();
+        let error = calibrator.read(io::Cursor::new("+1\n+2\nthree\n+4\n")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("Invalid change \"three\" at line 3"), "{}", error);
+        // The changes before the bad line are kept
+        assert_eq!(calibrator.sum(), 3);
+    }
+
+    #[test]
+    fn first_repeat_is_known_mid_stream() {
+        let mut calibrator = Calibrator::new();
+        calibrator.read(io::Cursor::new("+1\n")).unwrap();
+        assert_eq!(calibrator.first_repeat(), None);
+        calibrator.read(io::Cursor::new("-1\n")).unwrap();
+        assert_eq!(calibrator.first_repeat(), Some(0));
+        // Later repeats don't replace the first one
+        calibrator.extend(vec![5, -5, 5]);
+        assert_eq!((calibrator.sum(), calibrator.first_repeat()), (5, Some(0)));
+    }
+
+    #[test]
+    fn sums_past_i32() {
+        let calibrator = vec![i32::MAX as i64; 3].into_iter().collect::<Calibrator>();
+        assert_eq!(calibrator.sum(), 3 * i32::MAX as i64);
+        assert_eq!(part1(&vec![i32::MAX; 3]), 3 * i32::MAX as i64);
+    }
 }
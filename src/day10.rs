@@ -1,5 +1,7 @@
 #![allow(unused_imports, unused_variables)]
+use super::ocr;
 use super::prelude::*;
+use super::render::{Canvas, Frames, Pixel, Rgb};
 type Input = Vec<MovingPoint>;
 
 #[derive(Clone, Copy, FromStr)]
@@ -18,95 +20,309 @@ pub fn input_generator(input: &str) -> Input {
         .collect()
 }
 
-fn find_word(mut points: Vec<MovingPoint>) -> (String, usize) {
-    let (u, v) = points
-        .iter()
-        .minmax_by_key(|p| p.dx + p.dy)
-        .into_option()
-        .unwrap();
-    let (udx, udy, vdx, vdy) = (v.x - u.x, v.y - u.y, v.dx - u.dx, v.dy - u.dy);
+fn position(point: &MovingPoint, time: i64) -> (i64, i64) {
+    (point.x as i64 + point.dx as i64 * time, point.y as i64 + point.dy as i64 * time)
+}
 
-    let mut i = ((-vdx * udx - vdy * udy) / (vdx * vdx + vdy * vdy)) as usize;
+// Inclusive bounds of the points
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoundingBox {
+    pub min_x: i64,
+    pub min_y: i64,
+    pub max_x: i64,
+    pub max_y: i64,
+}
 
-    for point in points.iter_mut() {
-        point.x += point.dx * i as i32;
-        point.y += point.dy * i as i32;
+impl BoundingBox {
+    pub fn at(points: &[MovingPoint], time: i64) -> Self {
+        let positions = points.iter().map(|point| position(point, time));
+        let (min_x, max_x) = positions.clone().map(|p| p.0).minmax().into_option().expect("Input is empty");
+        let (min_y, max_y) = positions.map(|p| p.1).minmax().into_option().expect("Input is empty");
+        Self { min_x, min_y, max_x, max_y }
     }
 
-    loop {
-        let maxx = points.iter().map(|p| p.x).max().expect("Can't find maxx");
-        let minx = points.iter().map(|p| p.x).min().expect("Can't find minx");
-        let maxy = points.iter().map(|p| p.y).max().expect("Can't find maxy");
-        let miny = points.iter().map(|p| p.y).min().expect("Can't find miny");
-
-        let width = (maxx - minx + 1) as usize;
-        let height = (maxy - miny + 1) as usize;
-
-        if height == 10 {
-            const CHARMAP: [u64; 26] = [
-                0b001111111101000100001000010000100001000001000100000011111111, // A
-                0b111111111110001000011000100001100010000110001000010111011110, // B
-                0b011111111010000000011000000001100000000110000000010100000010, // C
-                0,                                                              // D
-                0b111111111110001000011000100001100010000110001000011000000001, // E
-                0b111111111110001000001000100000100010000010001000001000000000, // F
-                0b011111111010000000011000000001100001000110000100100100011111, // G
-                0b111111111100001000000000100000000010000000001000001111111111, // H
-                0,                                                              // I
-                0b000000011000000000010000000001100000000111111111101000000000, // J
-                0b111111111100001100000001001000001000010001000000101000000001, // K
-                0b111111111100000000010000000001000000000100000000010000000001, // L
-                0,                                                              // M
-                0b111111111101100000000001100000000001100000000001101111111111, // N
-                0,                                                              // O
-                0b111111111110001000001000100000100010000010001000000111000000, // P
-                0,                                                              // Q
-                0b111111111110001000001000100000100011000010001011000111000011, // R
-                0,                                                              // S
-                0,                                                              // T
-                0,                                                              // U
-                0,                                                              // V
-                0,                                                              // W
-                0b110000001100110011000000110000000011000000110011001100000011, // X
-                0,                                                              // Y
-                0b100000011110000010011000010001100010000110010000011110000001, // Z
-            ];
-
-            let mut grid = vec![false; width * height];
-            for point in points.iter() {
-                grid[(point.y - miny + (point.x - minx) * height as i32) as usize] = true;
-            }
+    pub fn width(&self) -> u64 { (self.max_x - self.min_x) as u64 + 1 }
+
+    pub fn height(&self) -> u64 { (self.max_y - self.min_y) as u64 + 1 }
+
+    pub fn area(&self) -> u128 { self.width() as u128 * self.height() as u128 }
+}
 
-            return (
-                (0..width * height)
-                    .step_by(10 * 6 + 10 * 2)
-                    .map(|start| {
-                        let encoded = grid[start..start + 60]
-                            .iter()
-                            .fold(0, |acc, &b| (acc << 1) + b as u64);
-                        let pos = CHARMAP
-                            .iter()
-                            .position(|&e| encoded == e)
-                            .expect("Unknown char");
-                        (pos as u8 + b'A') as char
-                    })
-                    .collect(),
-                i,
-            );
+pub struct Message {
+    pub time: i64,
+    pub bounds: BoundingBox,
+    // Lit positions relative to the top left corner of the bounds, sorted by row
+    pub points: Vec<(usize, usize)>,
+}
+
+impl Message {
+    pub fn grid(&self) -> Grid<bool> {
+        let width = self.bounds.width() as usize;
+        let mut grid = Grid { vec: vec![false; width * self.bounds.height() as usize], width };
+        for &(x, y) in self.points.iter() {
+            grid.vec[x + y * width] = true;
         }
+        grid
+    }
 
-        for point in points.iter_mut() {
-            point.x += point.dx;
-            point.y += point.dy;
+    // The letters, or the points drawn as text when they can't be read
+    pub fn text(&self) -> String {
+        ocr::recognize_or_art(&self.grid())
+    }
+}
+
+// First time in `from..=to` for which `pred` holds, or `to`, when it stays true once it is
+fn first_time(mut from: i64, mut to: i64, pred: impl Fn(i64) -> bool) -> i64 {
+    while from < to {
+        let mid = from + (to - from) / 2;
+        if pred(mid) {
+            to = mid;
+        } else {
+            from = mid + 1;
         }
-        i += 1;
     }
+    from
+}
+
+// First and last times in `from..=to` at which a function that decreases and then increases
+// is at its minimum
+fn minimum_times(from: i64, to: i64, f: impl Fn(i64) -> u128) -> (i64, i64) {
+    (first_time(from, to, |t| f(t + 1) >= f(t)), first_time(from, to, |t| f(t + 1) > f(t)))
+}
+
+// Times around which the largest of the values `a + b * t` switches from one line to another,
+// rounded both ways
+fn envelope_breaks(mut lines: Vec<(i64, i64)>) -> Vec<i64> {
+    lines.sort_unstable_by_key(|&(a, b)| (b, a));
+    let mut hull: Vec<(i64, i64)> = Vec::with_capacity(lines.len());
+    for (a, b) in lines {
+        if hull.last().map(|&(_, last)| last) == Some(b) {
+            hull.pop();
+        }
+        // The line before the last one is overtaken by the new one before the last one gets to it
+        while let [.., (a1, b1), (a2, b2)] = hull[..] {
+            if (a1 - a) as i128 * (b2 - b1) as i128 <= (a1 - a2) as i128 * (b - b1) as i128 {
+                hull.pop();
+            } else {
+                break;
+            }
+        }
+        hull.push((a, b));
+    }
+    hull.windows(2)
+        .map(|pair| (pair[0].0 - pair[1].0).div_euclid(pair[1].1 - pair[0].1))
+        .flat_map(|t| iter::once(t).chain(iter::once(t + 1)))
+        .collect()
+}
+
+// Time at which the bounding box of the points is the smallest. Width and height are convex
+// in time and two points can only cross while the time is within the span of the starting
+// coordinates, so it's enough to search that range on each axis. The area is smallest between
+// their minimums, where one grows and the other shrinks: between the times at which the sides
+// of the box switch points the area is concave, so only those times need to be checked.
+pub fn find_message(points: &[MovingPoint]) -> Message {
+    let start = BoundingBox::at(points, 0);
+    let span = max(start.width(), start.height()) as i64;
+
+    let (width_from, width_to) = minimum_times(-span, span, |t| BoundingBox::at(points, t).width() as u128);
+    let (height_from, height_to) = minimum_times(-span, span, |t| BoundingBox::at(points, t).height() as u128);
+    let time = if width_from <= height_to && height_from <= width_to {
+        // Both are at their minimum, any time here is as good: the one closest to the start
+        max(max(width_from, height_from), min(min(width_to, height_to), 0))
+    } else {
+        let (from, to) = if width_to < height_from { (width_to, height_from) } else { (height_to, width_from) };
+        let sides = [
+            points.iter().map(|p| (p.x as i64, p.dx as i64)).collect::<Vec<_>>(),
+            points.iter().map(|p| (-(p.x as i64), -(p.dx as i64))).collect(),
+            points.iter().map(|p| (p.y as i64, p.dy as i64)).collect(),
+            points.iter().map(|p| (-(p.y as i64), -(p.dy as i64))).collect(),
+        ];
+        let breaks = sides.iter().flat_map(|lines| envelope_breaks(lines.clone()));
+        iter::once(from)
+            .chain(iter::once(to))
+            .chain(breaks.filter(|t| (from..=to).contains(t)))
+            .min_by_key(|&t| (BoundingBox::at(points, t).area(), t))
+            .unwrap()
+    };
+
+    let bounds = BoundingBox::at(points, time);
+    let mut lit = points
+        .iter()
+        .map(|point| position(point, time))
+        .map(|(x, y)| ((x - bounds.min_x) as usize, (y - bounds.min_y) as usize))
+        .collect::<Vec<_>>();
+    lit.sort_unstable_by_key(|&(x, y)| (y, x));
+    lit.dedup();
+    Message { time, bounds, points: lit }
 }
 
 pub fn part1(points: &Input) -> String {
-    find_word(points.to_vec()).0
+    find_message(points).text()
 }
 
-pub fn part2(points: &Input) -> usize {
-    find_word(points.to_vec()).1
+pub fn part2(points: &Input) -> i64 {
+    find_message(points).time
+}
+
+fn render_points(points: &[MovingPoint], time: i64) -> Canvas {
+    let bounds = BoundingBox::at(points, time);
+    let mut canvas = Canvas::new(bounds.width() as usize, bounds.height() as usize, Pixel::new('.', Rgb::BLACK));
+    for (x, y) in points.iter().map(|point| position(point, time)) {
+        canvas.set((x - bounds.min_x) as usize, (y - bounds.min_y) as usize, Pixel::new('#', Rgb::WHITE));
+    }
+    canvas
+}
+
+// Renders a few ticks around the moment the message appears
+pub fn visualize(points: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    let time = find_message(points).time;
+    for t in time - 3..=time + 3 {
+        frames.push(&render_points(points, t))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    const EXAMPLE: &str = "\
+position=< 9,  1> velocity=< 0,  2>
+position=< 7,  0> velocity=<-1,  0>
+position=< 3, -2> velocity=<-1,  1>
+position=< 6, 10> velocity=<-2, -1>
+position=< 2, -4> velocity=< 2,  2>
+position=<-6, 10> velocity=< 2, -2>
+position=< 1,  8> velocity=< 1, -1>
+position=< 1,  7> velocity=< 1,  0>
+position=<-3, 11> velocity=< 1, -2>
+position=< 7,  6> velocity=<-1, -1>
+position=<-2,  3> velocity=< 1,  0>
+position=<-4,  3> velocity=< 2,  0>
+position=<10, -3> velocity=<-1,  1>
+position=< 5, 11> velocity=< 1, -2>
+position=< 4,  7> velocity=< 0, -1>
+position=< 8, -2> velocity=< 0,  1>
+position=<15,  0> velocity=<-2,  0>
+position=< 1,  6> velocity=< 1,  0>
+position=< 8,  9> velocity=< 0, -1>
+position=< 3,  3> velocity=<-1,  1>
+position=< 0,  5> velocity=< 0, -1>
+position=<-2,  2> velocity=< 2,  0>
+position=< 5, -2> velocity=< 1,  2>
+position=< 1,  4> velocity=< 2,  1>
+position=<-2,  7> velocity=< 2, -2>
+position=< 3,  6> velocity=<-1, -1>
+position=< 5,  0> velocity=< 1,  0>
+position=<-6,  0> velocity=< 2,  0>
+position=< 5,  9> velocity=< 1, -2>
+position=<14,  7> velocity=<-2,  0>
+position=<-3,  6> velocity=< 2, -1>";
+
+    // Points that draw `art` at `time`, each with its own velocity
+    fn converging(art: &str, time: i64, rng: &mut Lcg) -> Vec<MovingPoint> {
+        let lit = art.lines().enumerate().flat_map(|(y, row)| row.match_indices('#').map(move |(x, _)| (x as i32, y as i32)));
+        lit.map(|(x, y)| {
+            let (dx, dy) = (rng.range(-3, 4) as i32, rng.range(-3, 4) as i32);
+            MovingPoint { x: x - dx * time as i32, y: y - dy * time as i32, dx, dy }
+        })
+        .collect()
+    }
+
+    // Earliest time with the smallest bounding box
+    fn brute_force_time(points: &[MovingPoint], span: i64) -> i64 {
+        (-span..=span).min_by_key(|&t| (BoundingBox::at(points, t).area(), t)).unwrap()
+    }
+
+    #[test]
+    fn example() {
+        let message = find_message(&input_generator(EXAMPLE));
+        assert_eq!(message.time, 3);
+        assert_eq!(message.bounds, BoundingBox { min_x: 0, min_y: 0, max_x: 9, max_y: 7 });
+        // The letters are 8 rows tall, which no font has
+        let art = "\
+#...#..###
+#...#...#.
+#...#...#.
+#####...#.
+#...#...#.
+#...#...#.
+#...#...#.
+#...#..###
+";
+        assert_eq!(ocr::render_art(&message.grid()), art);
+        assert_eq!(message.text(), format!("\n{}", art));
+    }
+
+    #[test]
+    fn small_font_messages() {
+        let art = "\
+.##..####.#..#
+#..#....#.#..#
+#..#...#..####
+####..#...#..#
+#..#.#....#..#
+#..#.####.#..#";
+        let points = converging(art, 12, &mut Lcg::new(1));
+        let message = find_message(&points);
+        assert_eq!(message.time, 12);
+        assert_eq!(message.text(), "AZH");
+    }
+
+    #[test]
+    fn messages_before_the_start() {
+        let art = "\
+#####..#....#
+#....#.#....#
+#....#.#....#
+#....#.#....#
+#####..#....#
+#......#....#
+#......#....#
+#......#....#
+#......#....#
+#.......####.";
+        let points = converging(art, -40, &mut Lcg::new(2));
+        let message = find_message(&points);
+        assert_eq!(message.time, -40);
+        assert_eq!(message.text(), "PU");
+    }
+
+    #[test]
+    fn width_and_height_smallest_at_different_times() {
+        let mut rng = Lcg::new(3);
+        let mut between = 0;
+        for _ in 0..1000 {
+            // The columns come closest around time 10 and the rows around time 30
+            let points = (0..rng.range(2, 12))
+                .map(|_| {
+                    let (dx, dy) = (rng.range(-3, 4) as i32, rng.range(-3, 4) as i32);
+                    let (x, y) = (rng.range(0, 5) as i32, rng.range(0, 5) as i32);
+                    MovingPoint { x: x - dx * rng.range(5, 16) as i32, y: y - dy * rng.range(25, 36) as i32, dx, dy }
+                })
+                .collect::<Vec<_>>();
+            let start = BoundingBox::at(&points, 0);
+            let span = max(start.width(), start.height()) as i64;
+            let (_, width_to) = minimum_times(-span, span, |t| BoundingBox::at(&points, t).width() as u128);
+            let (height_from, _) = minimum_times(-span, span, |t| BoundingBox::at(&points, t).height() as u128);
+            if width_to >= height_from {
+                continue;
+            }
+            let time = find_message(&points).time;
+            assert_eq!(time, brute_force_time(&points, span));
+            between += (width_to < time && time < height_from) as usize;
+        }
+        // Sometimes the smallest box is strictly between the minimums, where a side switches points
+        assert!(between > 10, "{}", between);
+    }
+
+    #[test]
+    fn minimum_and_breaks() {
+        assert_eq!(minimum_times(-10, 10, |t| (t - 3).pow(2) as u128), (3, 3));
+        assert_eq!(minimum_times(-10, 10, |t| max((t - 2).abs(), 2) as u128), (0, 4));
+        // The larger of 10 - t and t switches at 5, the third line is never the largest
+        assert_eq!(envelope_breaks(vec![(0, 1), (10, -1), (0, 0)]), [5, 6]);
+        assert_eq!(envelope_breaks(vec![(0, 1), (1, 1)]), Vec::<i64>::new());
+    }
 }
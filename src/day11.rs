@@ -1,5 +1,7 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use super::json::Json;
+use super::runner::Answer;
 type Input = Vec<i32>;
 
 #[derive(Clone, Copy, Display, Debug)]
@@ -9,6 +11,24 @@ pub struct Tuple2(isize, isize);
 #[display("{0},{1},{2}")]
 pub struct Tuple3(isize, isize, isize);
 
+impl Answer for Tuple2 {
+    fn text(&self) -> String { self.to_string() }
+    fn value(&self) -> Json {
+        Json::object(vec![("x", Json::Int(self.0 as i128)), ("y", Json::Int(self.1 as i128))])
+    }
+}
+
+impl Answer for Tuple3 {
+    fn text(&self) -> String { self.to_string() }
+    fn value(&self) -> Json {
+        Json::object(vec![
+            ("x", Json::Int(self.0 as i128)),
+            ("y", Json::Int(self.1 as i128)),
+            ("size", Json::Int(self.2 as i128)),
+        ])
+    }
+}
+
 fn get2d(levels: &[i32], x: isize, y: isize) -> i32 {
     if x < 0 || x >= 300 || y < 0 || y >= 300 {
         return 0;
@@ -1,8 +1,9 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use super::cycle::{find_cycle, Strategy};
 type Input = (Vec<Pot>, Vec<Rule>);
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Display, FromStr)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Display, FromStr)]
 pub enum Pot {
     #[display("#")]
     Plant,
@@ -115,19 +116,21 @@ pub fn part2((pots, rules): &Input) -> isize {
         offset: 0,
     };
     pots_state.normalize();
-    let mut old_state = pots_state.clone();
 
-    for i in 0..50_000_000_000isize {
-        pots_state.next(rules);
-        if pots_state.state == old_state.state {
-            pots_state.offset += (pots_state.offset - old_state.offset) * (50_000_000_000 - (i + 1));
-            return pots_state.sum();
-        }
-        old_state.offset = pots_state.offset;
-        old_state.state.clear();
-        old_state.state.extend(pots_state.state.iter());
-        old_state.clone_from(&pots_state);
-    }
-
-    pots_state.sum()
+    let step = |pots_state: &PotsState| {
+        let mut next = pots_state.clone();
+        next.next(rules);
+        next
+    };
+    let cycle = find_cycle(&pots_state, step, |pots_state| pots_state.state.clone(), Strategy::Brent);
+
+    cycle
+        .translated_state_at(
+            50_000_000_000,
+            &pots_state,
+            step,
+            |pots_state| pots_state.offset,
+            |pots_state, drift| pots_state.offset += drift,
+        )
+        .sum()
 }
@@ -1,11 +1,22 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use super::grid_ext::GridExt;
+use super::json::Json;
+use super::runner::Answer;
+use super::render::{Frames, Pixel, Render, Rgb};
 type Input = (Grid<TrackDirection>, Vec<Cart>);
 
 #[derive(Clone, Copy, Display, Debug)]
 #[display("{0},{1}")]
 pub struct Tuple2(usize, usize);
 
+impl Answer for Tuple2 {
+    fn text(&self) -> String { self.to_string() }
+    fn value(&self) -> Json {
+        Json::object(vec![("x", Json::Int(self.0 as i128)), ("y", Json::Int(self.1 as i128))])
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum TrackDirection {
     XAxis,
@@ -34,8 +45,11 @@ impl Cart {
         Self { id, x, y, direction, crossroad_count: 0, crashed: false }
     }
     fn move_next(&mut self, grid: &Grid<TrackDirection>) {
-        self.x = (self.x as isize + self.direction.0) as usize;
-        self.y = (self.y as isize + self.direction.1) as usize;
+        let (x, y) = grid
+            .offset((self.x, self.y), (self.direction.0, self.direction.1))
+            .expect("A cart is off the grid!");
+        self.x = x;
+        self.y = y;
         
         let dir = self.direction;
         self.direction = match grid[(self.x, self.y)] {
@@ -56,32 +70,26 @@ impl Cart {
 }
 
 pub fn input_generator(input: &str) -> Input {
-    let grid = Grid {
-        vec: input.lines().flat_map(|line| {
-            line.chars().map(|c| match c {
-                '-' | '<' | '>' => TrackDirection::XAxis,
-                '|' | 'v' | '^' => TrackDirection::YAxis,
-                '/' => TrackDirection::Diag13,
-                '\\' => TrackDirection::Diag24,
-                '+' => TrackDirection::Crossroad,
-                ' ' => TrackDirection::Empty,
-                _ => panic!("Invalid input"),
-            })
-        }).collect(),
-        width: input.lines().nth(0).expect("Invalid input").len(),
-    };
-
-    let width = grid.width;
-    let carts = input.lines().enumerate().flat_map(|(y, line)| {
-        line.chars().enumerate().filter_map(move |(x, c)| match c {
-            'v' => Some(Cart::new(x + width * y, x, y, Direction(0, 1))),
-            '^' => Some(Cart::new(x + width * y, x, y, Direction(0, -1))),
-            '<' => Some(Cart::new(x + width * y, x, y, Direction(-1, 0))),
-            '>' => Some(Cart::new(x + width * y, x, y, Direction(1, 0))),
-            _ => None
-        })
-    })
-    .collect();
+    let mut carts = Vec::new();
+    let grid = Grid::from_char_map_enumerated(input, |(x, y), c| match c {
+        '-' => TrackDirection::XAxis,
+        '|' => TrackDirection::YAxis,
+        '/' => TrackDirection::Diag13,
+        '\\' => TrackDirection::Diag24,
+        '+' => TrackDirection::Crossroad,
+        ' ' => TrackDirection::Empty,
+        '<' | '>' => {
+            let dx = if c == '<' { -1 } else { 1 };
+            carts.push(Cart::new(carts.len(), x, y, Direction(dx, 0)));
+            TrackDirection::XAxis
+        }
+        '^' | 'v' => {
+            let dy = if c == '^' { -1 } else { 1 };
+            carts.push(Cart::new(carts.len(), x, y, Direction(0, dy)));
+            TrackDirection::YAxis
+        }
+        _ => panic!("Invalid input"),
+    });
 
     (grid, carts)
 }
@@ -125,3 +133,49 @@ pub fn part2((grid, carts): &Input) -> Tuple2 {
 
     carts.iter().filter(|c| !c.crashed).map(|c| Tuple2(c.x, c.y)).next().unwrap()
 }
+
+struct TrackState<'a> {
+    grid: &'a Grid<TrackDirection>,
+    carts: &'a [Cart],
+}
+
+impl Render for TrackState<'_> {
+    fn size(&self) -> (usize, usize) { (self.grid.width, self.grid.height()) }
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        if let Some(cart) = self.carts.iter().find(|c| c.x == x && c.y == y) {
+            if cart.crashed {
+                return Pixel::new('X', Rgb::RED);
+            }
+            let ch = match cart.direction {
+                Direction(0, 1) => 'v',
+                Direction(0, _) => '^',
+                Direction(1, _) => '>',
+                _ => '<',
+            };
+            return Pixel::new(ch, Rgb::YELLOW);
+        }
+        let ch = match self.grid[(x, y)] {
+            TrackDirection::XAxis => '-',
+            TrackDirection::YAxis => '|',
+            TrackDirection::Diag13 => '/',
+            TrackDirection::Diag24 => '\\',
+            TrackDirection::Crossroad => '+',
+            TrackDirection::Empty => ' ',
+        };
+        Pixel::new(ch, Rgb::GRAY)
+    }
+}
+
+pub fn visualize((grid, carts): &Input, frames: &mut Frames) -> std::io::Result<()> {
+    let mut carts = carts.clone();
+    frames.push(&TrackState { grid, carts: &carts })?;
+
+    while carts.len() > 1 {
+        next_tick(grid, &mut carts);
+        frames.push(&TrackState { grid, carts: &carts })?;
+        carts.retain(|c| !c.crashed);
+        carts.sort_by(|a, b| a.y.cmp(&b.y).then(a.x.cmp(&b.x)));
+    }
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use super::grid_ext::GridExt;
+use super::render::{Frames, Pixel, Render, Rgb};
 type Input = Stage;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -19,10 +21,8 @@ struct Soldier {
 
 type Point = (usize, usize);
 
-fn neighbours(point: Point) -> impl Iterator<Item=Point> {
-    [(-1, 0), (0, -1), (0, 1), (1, 0)]
-        .iter()
-        .map(move |&(dy, dx)| ((point.0 as isize + dy) as usize, (point.1 as isize + dx) as usize))
+fn neighbours(map: &Grid<StageTile>, (y, x): Point) -> impl Iterator<Item=Point> {
+    map.neighbours4((x, y)).map(|(x, y)| (y, x))
 }
 
 #[derive(Clone)]
@@ -36,7 +36,7 @@ pub struct Stage {
 
 impl Stage {
     fn try_direct_attack(&mut self, soldier: Soldier) -> bool {
-        let target = neighbours(soldier.pos)
+        let target = neighbours(&self.map, soldier.pos)
             .filter_map(|pos| self.soldiers_idx.get(&pos))
             .map(|&idx| self.soldiers[idx])
             .filter(|target| soldier.soldier_type != target.soldier_type && target.health > 0)
@@ -57,6 +57,10 @@ impl Stage {
     }
 
     fn play(&mut self) -> usize {
+        self.play_with(|_| {})
+    }
+
+    fn play_with(&mut self, mut on_round: impl FnMut(&Stage)) -> usize {
         let mut rounds_played = 0;
         let mut idxs = Vec::with_capacity(self.soldiers_idx.len());
         let mut seen = HashSet::new();
@@ -81,7 +85,7 @@ impl Stage {
                 seen.clear();
                 queue.clear();
                 queue.extend(
-                    neighbours(soldier.pos)
+                    neighbours(&self.map, soldier.pos)
                     .filter(|&pos| self.map[(pos.1, pos.0)] == StageTile::Empty)
                     .map(|pos| (pos, pos))
                 );
@@ -95,7 +99,7 @@ impl Stage {
                             }
                         } else {
                             queue.extend(
-                                neighbours(next_pos)
+                                neighbours(&self.map, next_pos)
                                 .filter(|&pos| self.map[(pos.1, pos.0)] == StageTile::Empty)
                                 .map(|pos| (pos, move_to_candidate))
                             );
@@ -118,6 +122,7 @@ impl Stage {
             }
 
             rounds_played += 1;
+            on_round(self);
         }
 
         rounds_played
@@ -129,36 +134,24 @@ pub fn input_generator(input: &str) -> Input {
     let mut elf_count = 0;
     let mut soldiers = Vec::new();
     let mut soldiers_idx = BTreeMap::new();
-    let mut grid_vec = Vec::new();
-
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            let tile = match c {
-                'G' => {
-                    goblin_count += 1;
-                    soldiers_idx.insert((y, x), soldiers.len());
-                    soldiers.push(Soldier { soldier_type: Goblin, health: 200, attack_power: 3, pos: (y, x)});
-                    StageTile::Empty
-                },
-                'E' => {
-                    elf_count += 1;
-                    soldiers_idx.insert((y, x), soldiers.len());
-                    soldiers.push(Soldier { soldier_type: Elf, health: 200, attack_power: 3, pos: (y, x) });
-                    StageTile::Empty
-                }
-                '.' => StageTile::Empty,
-                '#' => StageTile::Block,
-                _ => panic!("Invalid input"),
-            };
 
-            grid_vec.push(tile);
+    let map = Arc::new(Grid::from_char_map_enumerated(input, |(x, y), c| match c {
+        'G' => {
+            goblin_count += 1;
+            soldiers_idx.insert((y, x), soldiers.len());
+            soldiers.push(Soldier { soldier_type: Goblin, health: 200, attack_power: 3, pos: (y, x)});
+            StageTile::Empty
+        },
+        'E' => {
+            elf_count += 1;
+            soldiers_idx.insert((y, x), soldiers.len());
+            soldiers.push(Soldier { soldier_type: Elf, health: 200, attack_power: 3, pos: (y, x) });
+            StageTile::Empty
         }
-    }
-
-    let map = Arc::new(Grid {
-        vec: grid_vec,
-        width: input.lines().next().expect("Invalid input").len()
-    });
+        '.' => StageTile::Empty,
+        '#' => StageTile::Block,
+        _ => panic!("Invalid input"),
+    }));
 
     Stage { map, soldiers, soldiers_idx, goblin_count, elf_count }
 }
@@ -186,3 +179,33 @@ pub fn part2(input: &Input) -> usize {
         elf_attack_power += 1;
     }
 }
+
+impl Render for Stage {
+    fn size(&self) -> (usize, usize) { (self.map.width, self.map.height()) }
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        if let Some(&idx) = self.soldiers_idx.get(&(y, x)) {
+            let soldier = self.soldiers[idx];
+            let (ch, color) = match soldier.soldier_type {
+                Elf => ('E', Rgb::GREEN),
+                Goblin => ('G', Rgb::RED),
+            };
+            return Pixel::new(ch, Rgb::BLACK.mix(color, (55 + soldier.health.min(200)) as u8));
+        }
+        match self.map[(x, y)] {
+            StageTile::Block => Pixel::new('#', Rgb::GRAY),
+            StageTile::Empty => Pixel::new('.', Rgb::BLACK),
+        }
+    }
+}
+
+pub fn visualize(input: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    let mut stage = input.clone();
+    let mut result = frames.push(&stage);
+    stage.play_with(|stage| {
+        if result.is_ok() {
+            result = frames.push(stage);
+        }
+    });
+    result?;
+    frames.push(&stage)
+}
@@ -1,30 +1,18 @@
 #![allow(unused_imports)]
 use super::prelude::*;
+use super::grid_ext::GridExt;
+use super::render::{self, Frames, Pixel, Rgb};
 type Input = Grid<Cell>;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Cell { Flowing, Still, Empty, Wall }
 
 fn is_stagn(grid: &Grid<Cell>, (x, y): (usize, usize)) -> bool {
-    let mut tx = x + 1;
-    loop {
-        let cell = grid.get((tx, y)).copied();
-        if cell == Some(Cell::Wall) { break; }
-        if cell == None { return false; }
-        let under = grid[(tx, y+1)];
-        if under == Cell::Empty || under == Cell::Flowing { return false; }
-        tx += 1;
-    }
-    let mut tx = x - 1;
-    loop {
-        let cell = grid.get((tx, y)).copied();
-        if cell == Some(Cell::Wall) { break; }
-        if cell == None { return false; }
-        let under = grid[(tx, y+1)];
-        if under == Cell::Empty || under == Cell::Flowing { return false; }
-        tx -= 1;
-    }
-    true
+    let (row, under) = (grid.row(y), grid.row(y + 1));
+    let is_edge = |&tx: &usize| row[tx] == Cell::Wall || under[tx] == Cell::Empty || under[tx] == Cell::Flowing;
+    let right_edge = (x + 1..grid.width).find(is_edge);
+    let left_edge = (0..x).rev().find(is_edge);
+    right_edge.map(|tx| row[tx]) == Some(Cell::Wall) && left_edge.map(|tx| row[tx]) == Some(Cell::Wall)
 }
 
 fn fill_side(grid: &mut Grid<Cell>, fill: Cell, x: usize, y: usize, next: fn(usize) -> usize) -> usize {
@@ -42,6 +30,11 @@ fn fill_side(grid: &mut Grid<Cell>, fill: Cell, x: usize, y: usize, next: fn(usi
 }
 
 fn flow(grid: &mut Grid<Cell>) {
+    flow_with(grid, |_| {})
+}
+
+// `on_step` gets the grid every time some water moves
+fn flow_with(grid: &mut Grid<Cell>, mut on_step: impl FnMut(&Grid<Cell>)) {
     let mut x = 0;
     let mut y = 0;
 
@@ -49,15 +42,19 @@ fn flow(grid: &mut Grid<Cell>) {
         if cell == Cell::Flowing {
             match grid.get((x, y+1)) {
                 Some(Cell::Still) | Some(Cell::Wall) => {
-                    let fill = if is_stagn(&grid, (x, y)) { Cell::Still } else { Cell::Flowing };
+                    let fill = if is_stagn(grid, (x, y)) { Cell::Still } else { Cell::Flowing };
                     grid[(x, y)] = fill;
                     let leftmost = fill_side(grid, fill, x, y, |x| x-1);
                     let rightmost = fill_side(grid, fill, x, y, |x| x+1);
                     x = if fill == Cell::Still { leftmost } else { rightmost };
                     if fill == Cell::Still { y -= 1; }
+                    on_step(grid);
                     continue 'outer;
                 },
-                Some(Cell::Empty) => grid[(x, y+1)] = Cell::Flowing,
+                Some(Cell::Empty) => {
+                    grid[(x, y+1)] = Cell::Flowing;
+                    on_step(grid);
+                },
                 _ => {},
             }
         }
@@ -128,3 +125,82 @@ pub fn part2(input: &Input) -> usize {
         .filter(|&&cell| cell == Cell::Still)
         .count()
 }
+
+fn render_cell(cell: &Cell) -> Pixel {
+    match cell {
+        Cell::Flowing => Pixel::new('|', Rgb::BLUE.mix(Rgb::WHITE, 128)),
+        Cell::Still => Pixel::new('~', Rgb::BLUE),
+        Cell::Empty => Pixel::new('.', Rgb::BLACK),
+        Cell::Wall => Pixel::new('#', Rgb::BROWN),
+    }
+}
+
+// Writing the whole grid every time the water moves would take tens of thousands of frames,
+// so the steps are sampled evenly: `--ticks` frames (100 by default) plus the initial state.
+pub fn visualize(input: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    let mut steps = 0;
+    flow_with(&mut input.clone(), |_| steps += 1);
+    let samples = frames.ticks().unwrap_or(100);
+    // Index of the sample the step falls in, the last step always ends a sample
+    let sample = |step: usize| step * samples / max(steps, 1);
+
+    let mut grid = input.clone();
+    let mut result = frames.push(&render::from_grid(&grid, render_cell));
+    let mut step = 0;
+    flow_with(&mut grid, |grid| {
+        step += 1;
+        if result.is_ok() && sample(step) > sample(step - 1) {
+            result = frames.push(&render::from_grid(grid, render_cell));
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Backend;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    const EXAMPLE: &str = "\
+x=495, y=2..7
+y=7, x=495..501
+x=501, y=3..7
+x=498, y=2..4
+x=506, y=1..2
+x=498, y=10..13
+x=504, y=10..13
+y=13, x=498..504";
+
+    #[test]
+    fn example() {
+        let grid = input_generator(EXAMPLE);
+        assert_eq!((part1(&grid), part2(&grid)), (57, 29));
+    }
+
+    #[test]
+    fn visualize_samples_the_steps() {
+        let grid = input_generator(EXAMPLE);
+        let mut steps = 0;
+        flow_with(&mut grid.clone(), |_| steps += 1);
+        assert!(steps > 10);
+
+        let temp = TempDir::new("day17");
+        let mut frames = Frames::create(temp.path(), Backend::Ascii).unwrap().with_ticks(Some(5));
+        visualize(&grid, &mut frames).unwrap();
+        let mut names = fs::read_dir(temp.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        // The initial state and 5 samples, the last of which is the final state
+        assert_eq!(names.len(), 6, "{:?}", names);
+
+        let mut flowed = grid.clone();
+        flow(&mut flowed);
+        let last = fs::read_to_string(temp.path().join(names.last().unwrap())).unwrap();
+        let expected = flowed.display_with(|&cell| render_cell(&cell).ch).to_string();
+        assert_eq!(last, expected);
+    }
+}
@@ -1,27 +1,21 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use super::cycle::{find_cycle, Strategy};
+use super::grid_ext::GridExt;
+use super::render::{self, Frames, Pixel, Rgb};
 type Input = Grid<Acre>;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Acre { Open, Tree, Lumberyard }
 
-fn neighbours(grid: &Grid<Acre>, x: usize, y: usize) -> impl Iterator<Item = Acre> + '_ {
-    (-1..=1)
-        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
-        .filter(|&(dx, dy)| !(dx == 0 && dy == 0))
-        .filter_map(move |(dx, dy)| Some(((x as isize + dx).try_into().ok()?, (y as isize + dy).try_into().ok()?)))
-        .filter_map(move |(x, y)| grid.get((x, y)))
-        .copied()
-}
-
 fn next_tick(grid: &Grid<Acre>, buffer: &mut Grid<Acre>) {
     for x in 0..grid.width {
         for y in 0..grid.height() {
             let mut open_count = 0;
             let mut tree_count = 0;
             let mut lumberyard_count = 0;
-            for neighbour in neighbours(grid, x, y) {
-                match neighbour {
+            for neighbour in grid.neighbours8((x, y)) {
+                match grid[neighbour] {
                     Acre::Open => open_count += 1,
                     Acre::Tree => tree_count += 1,
                     Acre::Lumberyard => lumberyard_count += 1,
@@ -53,18 +47,12 @@ fn resource_value(grid: &Grid<Acre>) -> usize {
 }
 
 pub fn input_generator(input: &str) -> Input {
-    Grid {
-        vec: input.lines().flat_map(|line| {
-            line.chars().map(|c| match c {
-                '.' => Acre::Open,
-                '|' => Acre::Tree,
-                '#' => Acre::Lumberyard,
-                _ => panic!("Invalid input")
-            })
-        })
-        .collect(),
-        width: input.lines().next().expect("Invalid input").len()
-    }
+    Grid::from_char_map(input, |c| match c {
+        '.' => Acre::Open,
+        '|' => Acre::Tree,
+        '#' => Acre::Lumberyard,
+        _ => panic!("Invalid input")
+    })
 }
 
 pub fn part1(input: &Input) -> usize {
@@ -78,23 +66,32 @@ pub fn part1(input: &Input) -> usize {
 }
 
 pub fn part2(input: &Input) -> usize {
+    let step = |grid: &Grid<Acre>| {
+        let mut next = grid.clone();
+        next_tick(grid, &mut next);
+        next
+    };
+    let cycle = find_cycle(input, step, Grid::clone, Strategy::Hash);
+    resource_value(&cycle.state_at(1_000_000_000, input, step))
+}
+
+fn render_acre(acre: &Acre) -> Pixel {
+    match acre {
+        Acre::Open => Pixel::new('.', Rgb::BROWN),
+        Acre::Tree => Pixel::new('|', Rgb::GREEN),
+        Acre::Lumberyard => Pixel::new('#', Rgb::GRAY),
+    }
+}
+
+// One frame per tick, for as many ticks as part 1 unless asked otherwise
+pub fn visualize(input: &Input, frames: &mut Frames) -> std::io::Result<()> {
     let mut grid = input.clone();
     let mut buffer = input.clone();
-    let mut cache = HashMap::<Grid<Acre>, usize>::new();
-
-    for cycle in 1..=1_000_000_000 {
+    frames.push(&render::from_grid(&grid, render_acre))?;
+    for _ in 0..frames.ticks().unwrap_or(10) {
         next_tick(&grid, &mut buffer);
         swap(&mut grid, &mut buffer);
-        if let Some(&prev_cycle) = cache.get(&grid) {
-            let final_idx = prev_cycle + (1_000_000_000 - cycle) % (cycle - prev_cycle);
-            return resource_value(
-                cache.iter()
-                    .find(|&(_, &idx)| idx == final_idx)
-                    .unwrap()
-                    .0
-            );
-        }
-        cache.insert(grid.clone(), cycle);
+        frames.push(&render::from_grid(&grid, render_acre))?;
     }
-    resource_value(&grid)
+    Ok(())
 }
@@ -17,29 +17,36 @@ pub struct Instruction {
     c: usize,
 }
 
+pub fn step(ip: usize, instructions: &[Instruction], regs: &mut [usize; 6]) -> bool {
+    let instruction = match instructions.get(regs[ip]) {
+        Some(&instruction) => instruction,
+        None => return false,
+    };
+    let Instruction { opcode, a, b, c} = instruction;
+    regs[c] = match opcode {
+        OpCode::Addr => regs[a] + regs[b],
+        OpCode::Addi => regs[a] + b,
+        OpCode::Mulr => regs[a] * regs[b],
+        OpCode::Muli => regs[a] * b,
+        OpCode::Banr => regs[a] & regs[b],
+        OpCode::Bani => regs[a] & b,
+        OpCode::Borr => regs[a] | regs[b],
+        OpCode::Bori => regs[a] | b,
+        OpCode::Setr => regs[a],
+        OpCode::Seti => a,
+        OpCode::Gtir => (a > regs[b]) as usize,
+        OpCode::Gtri => (regs[a] > b) as usize,
+        OpCode::Gtrr => (regs[a] > regs[b]) as usize,
+        OpCode::Eqir => (a == regs[b]) as usize,
+        OpCode::Eqri => (regs[a] == b) as usize,
+        OpCode::Eqrr => (regs[a] == regs[b]) as usize,
+    };
+    regs[ip] += 1;
+    true
+}
+
 pub fn run(ip: usize, instructions: &[Instruction], regs: &mut [usize; 6]) {
-    while let Some(&instruction) = instructions.get(regs[ip]) {
-        let Instruction { opcode, a, b, c} = instruction;
-        regs[c] = match opcode {
-            OpCode::Addr => regs[a] + regs[b],
-            OpCode::Addi => regs[a] + b,
-            OpCode::Mulr => regs[a] * regs[b],
-            OpCode::Muli => regs[a] * b,
-            OpCode::Banr => regs[a] & regs[b],
-            OpCode::Bani => regs[a] & b,
-            OpCode::Borr => regs[a] | regs[b],
-            OpCode::Bori => regs[a] | b,
-            OpCode::Setr => regs[a],
-            OpCode::Seti => a,
-            OpCode::Gtir => (a > regs[b]) as usize,
-            OpCode::Gtri => (regs[a] > b) as usize,
-            OpCode::Gtrr => (regs[a] > regs[b]) as usize,
-            OpCode::Eqir => (a == regs[b]) as usize,
-            OpCode::Eqri => (regs[a] == b) as usize,
-            OpCode::Eqrr => (regs[a] == regs[b]) as usize,
-        };
-        regs[ip] += 1;
-    }
+    while step(ip, instructions, regs) {}
 }
 
 pub fn input_generator(input: &str) -> Input {
@@ -68,13 +75,15 @@ pub fn part1(input: &Input) -> usize {
 }
 
 pub fn part2(input: &Input) -> usize {
-    // let &(ip, ref instructions) = input;
-    // let mut registers = [0; 6];
-    // registers[0] = 1;
-    // run(ip, instructions, &mut registers);
-    // registers[0]
+    let &(ip, ref instructions) = input;
+    let mut registers = [0; 6];
+    registers[0] = 1;
+
+    // The program jumps to a setup routine that computes a big number, then jumps back to
+    // instruction 1 where it slowly sums its divisors. Stop there and do the sum directly.
+    while step(ip, instructions, &mut registers) && registers[ip] != 1 {}
 
-    let n = 10551276;
+    let n = registers.iter().copied().max().unwrap();
     (1..=n)
         .filter(|&i| n % i == 0)
         .sum()
@@ -1,42 +1,182 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use std::collections::BTreeSet;
 type Input<'a> = &'a str;
 
 pub fn input_generator(input: &str) -> Input { input }
 
-pub fn part1(input: &Input) -> usize {
-    let mut count_two = 0;
-    let mut count_three = 0;
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Checksum<'a> {
+    // For each requested multiplicity, the IDs having some char that appears exactly that many times
+    pub ids: BTreeMap<usize, Vec<&'a str>>,
+}
+
+impl Checksum<'_> {
+    #[allow(dead_code)]
+    pub fn tally(&self, multiplicity: usize) -> usize {
+        self.ids.get(&multiplicity).map_or(0, Vec::len)
+    }
+
+    pub fn tallies(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.ids.iter().map(|(&multiplicity, ids)| (multiplicity, ids.len()))
+    }
 
-    for line in input.lines() {
-        let mut counter = HashMap::new();
-        for c in line.chars() {
+    pub fn product(&self) -> usize {
+        self.tallies().map(|(_, tally)| tally).product()
+    }
+}
+
+// Counts chars rather than bytes, so any Unicode ID works
+pub fn checksum<'a>(ids: impl IntoIterator<Item = &'a str>, multiplicities: &[usize]) -> Checksum<'a> {
+    let mut result = multiplicities.iter().map(|&m| (m, Vec::new())).collect::<BTreeMap<_, _>>();
+    let mut counter = HashMap::new();
+
+    for id in ids {
+        counter.clear();
+        for c in id.chars() {
             *counter.entry(c).or_insert(0) += 1;
         }
-        if counter.values().any(|c| *c == 2) {
-            count_two += 1;
-        }
-        if counter.values().any(|c| *c == 3) {
-            count_three += 1;
+        let counts = counter.values().copied().collect::<HashSet<usize>>();
+        for (multiplicity, matching) in result.iter_mut() {
+            if counts.contains(multiplicity) {
+                matching.push(id);
+            }
         }
     }
 
-    count_two * count_three
+    Checksum { ids: result }
+}
+
+pub fn part1(input: &Input) -> usize {
+    checksum(input.lines(), &[2, 3]).product()
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NearDuplicate<'a> {
+    pub first: &'a str,
+    pub second: &'a str,
+    // Char positions where the two IDs differ
+    pub positions: Vec<usize>,
 }
 
-pub fn part2(input: &Input) -> String {
-    for (i, line) in input.lines().enumerate() {
-        for other in input.lines().skip(i + 1) {
-            let similar_chars = line
-                .chars()
-                .zip(other.chars())
-                .filter(|(a, b)| a == b)
-                .map(|(c, _)| c);
-            if similar_chars.clone().count() == line.len() - 1 {
-                return similar_chars.collect();
+// Finds every pair of IDs that differ in at least 1 and at most `k` chars, in input order.
+// IDs are grouped by length, since IDs of different lengths are never near duplicates. Then
+// for each choice of `k` positions the IDs are bucketed by their chars outside those positions,
+// so two IDs share a bucket exactly when all their differences are in the masked positions.
+pub fn near_duplicates<'a>(ids: &[&'a str], k: usize) -> Vec<NearDuplicate<'a>> {
+    let chars = ids.iter().map(|id| id.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+
+    let mut by_length = HashMap::<usize, Vec<usize>>::new();
+    for (idx, id) in chars.iter().enumerate() {
+        by_length.entry(id.len()).or_default().push(idx);
+    }
+
+    let mut pairs = BTreeSet::new();
+    for (&length, group) in by_length.iter() {
+        for masked in (0..length).combinations(min(k, length)) {
+            let mut buckets = HashMap::<Vec<char>, Vec<usize>>::new();
+            for &idx in group {
+                let key = chars[idx]
+                    .iter()
+                    .enumerate()
+                    .filter(|(pos, _)| !masked.contains(pos))
+                    .map(|(_, &c)| c)
+                    .collect();
+                buckets.entry(key).or_default().push(idx);
+            }
+            for bucket in buckets.values().filter(|bucket| bucket.len() > 1) {
+                pairs.extend(bucket.iter().copied().tuple_combinations::<(_, _)>());
             }
         }
     }
 
-    panic!("No lines are similar");
+    pairs
+        .into_iter()
+        .filter_map(|(i, j)| {
+            let positions = (0..chars[i].len()).filter(|&pos| chars[i][pos] != chars[j][pos]).collect::<Vec<_>>();
+            if positions.is_empty() {
+                return None;
+            }
+            Some(NearDuplicate { first: ids[i], second: ids[j], positions })
+        })
+        .collect()
+}
+
+// The chars in common between the first two IDs that differ in exactly one position
+pub fn part2(input: &Input) -> Option<String> {
+    let ids = input.lines().collect::<Vec<_>>();
+    let pair = near_duplicates(&ids, 1).into_iter().next()?;
+    let common = pair.first
+        .chars()
+        .enumerate()
+        .filter(|(pos, _)| !pair.positions.contains(pos))
+        .map(|(_, c)| c)
+        .collect();
+    Some(common)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [&str; 7] = ["abcdef", "bababc", "abbcde", "abcccd", "aabcdd", "abcdee", "ababab"];
+
+    #[test]
+    fn checksum_example() {
+        let checksum = checksum(EXAMPLE.iter().copied(), &[2, 3]);
+        assert_eq!((checksum.tally(2), checksum.tally(3)), (4, 3));
+        assert_eq!(checksum.tallies().collect::<Vec<_>>(), [(2, 4), (3, 3)]);
+        assert_eq!(checksum.product(), 12);
+        assert_eq!(checksum.ids[&2], ["bababc", "abbcde", "aabcdd", "abcdee"]);
+        assert_eq!(checksum.ids[&3], ["bababc", "abcccd", "ababab"]);
+        // Multiplicities that weren't asked for aren't counted
+        assert_eq!(checksum.tally(1), 0);
+    }
+
+    #[test]
+    fn checksum_counts_chars() {
+        let checksum = checksum(vec!["ééa", "日本日本日", "ñ"], &[1, 2, 3]);
+        assert_eq!(checksum.ids[&1], ["ééa", "ñ"]);
+        assert_eq!(checksum.ids[&2], ["ééa", "日本日本日"]);
+        assert_eq!(checksum.ids[&3], ["日本日本日"]);
+    }
+
+    fn pair<'a>(first: &'a str, second: &'a str, positions: &[usize]) -> NearDuplicate<'a> {
+        NearDuplicate { first, second, positions: positions.to_vec() }
+    }
+
+    #[test]
+    fn part2_example() {
+        let input = "abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz";
+        assert_eq!(part2(&input), Some("fgij".to_string()));
+    }
+
+    #[test]
+    fn part2_without_similar_ids() {
+        assert_eq!(part2(&"abcde\nfghij\nabxye"), None);
+        assert_eq!(part2(&""), None);
+    }
+
+    #[test]
+    fn pairs_within_distance() {
+        let ids = ["abcde", "axcye", "abcdx", "fghij", "abcde"];
+        assert_eq!(near_duplicates(&ids, 1), [pair("abcde", "abcdx", &[4]), pair("abcdx", "abcde", &[4])]);
+        assert_eq!(
+            near_duplicates(&ids, 2),
+            [
+                pair("abcde", "axcye", &[1, 3]),
+                pair("abcde", "abcdx", &[4]),
+                pair("axcye", "abcde", &[1, 3]),
+                pair("abcdx", "abcde", &[4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unequal_lengths_never_match() {
+        let ids = ["abc", "abcd", "abd", "ab", "xbcd"];
+        assert_eq!(near_duplicates(&ids, 1), [pair("abc", "abd", &[2]), pair("abcd", "xbcd", &[0])]);
+        // Masking more positions than an ID has
+        assert_eq!(near_duplicates(&["ab", "xy"], 5), [pair("ab", "xy", &[0, 1])]);
+    }
 }
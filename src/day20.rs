@@ -1,5 +1,6 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use super::render::{Canvas, Frames, Pixel, Rgb};
 type Input = Vec<Instruction>;
 
 type Point = (isize, isize);
@@ -95,3 +96,32 @@ pub fn part2(input: &Input) -> usize {
     let distances = distances(&map);
     distances.filter(|&(_, dist)| dist >= 1000).count()
 }
+
+pub fn visualize(input: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    let map = create_map(input);
+    let distances = distances(&map).collect::<HashMap<_, _>>();
+    let max_distance = distances.values().copied().max().unwrap_or(0).max(1);
+
+    let (minx, maxx) = map.keys().map(|p| p.0).minmax().into_option().unwrap_or((0, 0));
+    let (miny, maxy) = map.keys().map(|p| p.1).minmax().into_option().unwrap_or((0, 0));
+
+    // Each room takes the odd coordinates, doors and walls the even ones. North is up.
+    let to_canvas = |(x, y): Point| ((2 * (x - minx) + 1) as usize, (2 * (maxy - y) + 1) as usize);
+    let width = (2 * (maxx - minx) + 3) as usize;
+    let height = (2 * (maxy - miny) + 3) as usize;
+    let mut canvas = Canvas::new(width, height, Pixel::new('#', Rgb::GRAY));
+
+    for (&room, neighbours) in map.iter() {
+        let t = (distances[&room] * 255 / max_distance) as u8;
+        let ch = if room == (0, 0) { 'X' } else { '.' };
+        let (cx, cy) = to_canvas(room);
+        canvas.set(cx, cy, Pixel::new(ch, Rgb::GREEN.mix(Rgb::RED, t)));
+        for &next in neighbours.iter() {
+            let (nx, ny) = to_canvas(next);
+            let door = if nx == cx { '-' } else { '|' };
+            canvas.set((cx + nx) / 2, (cy + ny) / 2, Pixel::new(door, Rgb::WHITE));
+        }
+    }
+
+    frames.push(&canvas)
+}
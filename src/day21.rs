@@ -60,24 +60,39 @@ pub fn input_generator(input: &str) -> Input {
     (ip, instructions)
 }
 
+// The program halts when a value it computes is equal to register 0. Returns the address
+// of that comparison and the register the value is in.
+fn halt_check(instructions: &[Instruction]) -> (usize, usize) {
+    instructions.iter()
+        .enumerate()
+        .find_map(|(addr, instruction)| match *instruction {
+            Instruction { opcode: OpCode::Eqrr, a, b: 0, .. } => Some((addr, a)),
+            Instruction { opcode: OpCode::Eqrr, a: 0, b, .. } => Some((addr, b)),
+            _ => None,
+        })
+        .expect("Invalid input: the program never compares with register 0")
+}
+
 pub fn part1(input: &Input) -> usize {
     let &(ip, ref instructions) = input;
+    let (check_addr, value_reg) = halt_check(instructions);
     let mut registers = [0; 6];
-    while registers[ip] != 28 {
+    while registers[ip] != check_addr {
         run_next(ip, instructions, &mut registers);
     }
-    registers[3]
+    registers[value_reg]
 }
 
 pub fn part2(input: &Input) -> usize {
     let &(ip, ref instructions) = input;
+    let (check_addr, value_reg) = halt_check(instructions);
     let mut registers = [0; 6];
     let mut seen = HashSet::new();
     let mut last_seen = None;
     loop {
-        if registers[ip] == (instructions.len()-1) - 2 {
-            if seen.insert(registers[3]) {
-                last_seen = Some(registers[3]);
+        if registers[ip] == check_addr {
+            if seen.insert(registers[value_reg]) {
+                last_seen = Some(registers[value_reg]);
             } else {
                 break;
             }
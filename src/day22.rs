@@ -1,5 +1,6 @@
 #[allow(unused_imports)]
 use super::prelude::*;
+use super::render::{Canvas, Frames, Pixel, Rgb};
 type Input = (usize, (usize, usize));
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -109,3 +110,26 @@ pub fn part2(input: &Input) -> usize {
 
     unreachable!();
 }
+
+// Renders the cave up to a few regions past the target
+pub fn visualize(input: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    let &(depth, target) = input;
+    let mut cave = Cave { er_levels: Vec::new(), depth, target };
+    let (width, height) = (target.0 + 6, target.1 + 6);
+
+    let mut canvas = Canvas::new(width, height, Pixel::new(' ', Rgb::BLACK));
+    for x in 0..width {
+        for y in 0..height {
+            let pixel = match er_level_at(&mut cave, x, y) % 3 {
+                0 => Pixel::new('.', Rgb::GRAY),
+                1 => Pixel::new('=', Rgb::BLUE),
+                _ => Pixel::new('|', Rgb::BROWN),
+            };
+            canvas.set(x, y, pixel);
+        }
+    }
+    canvas.set(0, 0, Pixel::new('M', Rgb::YELLOW));
+    canvas.set(target.0, target.1, Pixel::new('T', Rgb::YELLOW));
+
+    frames.push(&canvas)
+}
@@ -1,6 +1,7 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
-type Input = (Vec<Claim>, Grid<usize>);
+use super::render::{self, Frames, Pixel, Rgb};
+type Input = Vec<Claim>;
 
 #[derive(FromStr, Debug, Copy, Clone)]
 #[from_str(regex = r"(?m)#(?P<id>\d+) @ (?P<x>\d+),(?P<y>\d+): (?P<width>\d+)x(?P<height>\d+)")]
@@ -12,52 +13,496 @@ pub struct Claim {
     height: usize,
 }
 
+impl Claim {
+    #[allow(dead_code)]
+    pub fn new(id: u16, rect: Rect) -> Self {
+        Self { id, x: rect.x, y: rect.y, width: rect.width, height: rect.height }
+    }
+
+    #[allow(dead_code)]
+    pub fn id(&self) -> u16 { self.id }
+
+    pub fn rect(&self) -> Rect {
+        Rect { x: self.x, y: self.y, width: self.width, height: self.height }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    fn x_end(&self) -> usize { self.x + self.width }
+    fn y_end(&self) -> usize { self.y + self.height }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.x <= x && x < self.x_end() && self.y <= y && y < self.y_end()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let (x, x_end) = (max(self.x, other.x), min(self.x_end(), other.x_end()));
+        let (y, y_end) = (max(self.y, other.y), min(self.y_end(), other.y_end()));
+        if x < x_end && y < y_end {
+            Some(Rect { x, y, width: x_end - x, height: y_end - y })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Overlap {
+    pub first: u16,
+    pub second: u16,
+    pub area: Rect,
+}
+
+// Segment tree over the compressed x coordinates. Each node knows how many claims cover
+// its whole range, and how much of its range is covered at least once and at least twice.
+struct CoverTree {
+    xs: Vec<usize>,
+    cover: Vec<u32>,
+    once: Vec<usize>,
+    twice: Vec<usize>,
+}
+
+impl CoverTree {
+    fn new(xs: Vec<usize>) -> Self {
+        let size = 4 * xs.len().max(1);
+        Self { xs, cover: vec![0; size], once: vec![0; size], twice: vec![0; size] }
+    }
+
+    fn covered_twice(&self) -> usize { self.twice[1] }
+
+    // Adds `delta` to the coverage of the segments `from..to`, the node spans `lo..hi`
+    fn update(&mut self, node: usize, lo: usize, hi: usize, from: usize, to: usize, delta: i32) {
+        if to <= lo || hi <= from {
+            return;
+        }
+        if from <= lo && hi <= to {
+            self.cover[node] = (self.cover[node] as i32 + delta) as u32;
+        } else {
+            let mid = (lo + hi) / 2;
+            self.update(2 * node, lo, mid, from, to, delta);
+            self.update(2 * node + 1, mid, hi, from, to, delta);
+        }
+
+        let len = self.xs[hi] - self.xs[lo];
+        let is_leaf = hi - lo == 1;
+        let children = |v: &[usize]| if is_leaf { 0 } else { v[2 * node] + v[2 * node + 1] };
+        let (once, twice) = match self.cover[node] {
+            0 => (children(&self.once), children(&self.twice)),
+            1 => (len, children(&self.once)),
+            _ => (len, len),
+        };
+        self.once[node] = once;
+        self.twice[node] = twice;
+    }
+}
+
+// Sweeps a horizontal line down the fabric, keeping the coverage of the x axis in a segment tree
+pub fn overlap_area(claims: &[Claim]) -> usize {
+    let mut xs = claims.iter().flat_map(|c| iter::once(c.x).chain(iter::once(c.x + c.width))).collect::<Vec<_>>();
+    xs.sort_unstable();
+    xs.dedup();
+    if xs.len() < 2 {
+        return 0;
+    }
+
+    let mut events = claims
+        .iter()
+        .filter(|c| c.width != 0 && c.height != 0)
+        .flat_map(|c| {
+            let from = xs.binary_search(&c.x).unwrap();
+            let to = xs.binary_search(&(c.x + c.width)).unwrap();
+            iter::once((c.y, 1, from, to)).chain(iter::once((c.y + c.height, -1, from, to)))
+        })
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+
+    let segments = xs.len() - 1;
+    let mut tree = CoverTree::new(xs);
+    let mut area = 0;
+    let mut last_y = 0;
+    for (y, delta, from, to) in events {
+        area += tree.covered_twice() * (y - last_y);
+        last_y = y;
+        tree.update(1, 0, segments, from, to, delta);
+    }
+    area
+}
+
+#[allow(dead_code)]
+pub fn claims_at(claims: &[Claim], x: usize, y: usize) -> impl Iterator<Item = u16> + '_ {
+    claims.iter().filter(move |c| c.rect().contains(x, y)).map(|c| c.id)
+}
+
+// Claims crossing the sweep line, stored by their position in y order. Each node of the
+// segment tree keeps the largest y end among the active claims below it, so the claims
+// crossing a range of y are found without visiting the ones that don't.
+struct ActiveClaims<'a> {
+    by_y: Vec<&'a Claim>,
+    max_end: Vec<usize>,
+    size: usize,
+}
+
+impl<'a> ActiveClaims<'a> {
+    fn new(by_y: Vec<&'a Claim>) -> Self {
+        let size = by_y.len().next_power_of_two();
+        Self { by_y, max_end: vec![0; 2 * size], size }
+    }
+
+    // An inactive claim has end 0, which is never past the start of a range
+    fn set_end(&mut self, slot: usize, end: usize) {
+        let mut node = slot + self.size;
+        self.max_end[node] = end;
+        while node > 1 {
+            node /= 2;
+            self.max_end[node] = max(self.max_end[2 * node], self.max_end[2 * node + 1]);
+        }
+    }
+
+    // Active claims starting before `y_end` and ending after `y`
+    fn crossing(&self, y: usize, y_end: usize, out: &mut Vec<&'a Claim>) {
+        let limit = self.by_y.partition_point(|c| c.y < y_end);
+        self.collect(1, 0, self.size, limit, y, out);
+    }
+
+    fn collect(&self, node: usize, lo: usize, hi: usize, limit: usize, y: usize, out: &mut Vec<&'a Claim>) {
+        if lo >= limit || self.max_end[node] <= y {
+            return;
+        }
+        if hi - lo == 1 {
+            out.push(self.by_y[lo]);
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.collect(2 * node, lo, mid, limit, y, out);
+        self.collect(2 * node + 1, mid, hi, limit, y, out);
+    }
+}
+
+// Sweeps a vertical line left to right, comparing each claim only with the ones it crosses
+// that also share some of its rows. Takes O((n + k) log n) for `k` overlapping pairs.
+pub fn overlapping_pairs(claims: &[Claim]) -> Vec<Overlap> {
+    let claims = claims.iter().filter(|c| c.width != 0 && c.height != 0).collect::<Vec<_>>();
+    let mut by_y = (0..claims.len()).collect::<Vec<_>>();
+    by_y.sort_unstable_by_key(|&idx| claims[idx].y);
+    let mut slots = vec![0; claims.len()];
+    for (slot, &idx) in by_y.iter().enumerate() {
+        slots[idx] = slot;
+    }
+    let mut by_x = (0..claims.len()).collect::<Vec<_>>();
+    by_x.sort_unstable_by_key(|&idx| claims[idx].x);
+
+    let mut active = ActiveClaims::new(by_y.iter().map(|&idx| claims[idx]).collect());
+    let mut ending = BinaryHeap::new();
+    let mut crossing = Vec::new();
+    let mut overlaps = Vec::new();
+    for idx in by_x {
+        let (id, rect) = (claims[idx].id, claims[idx].rect());
+        while let Some(&Reverse((x_end, other))) = ending.peek() {
+            if x_end > rect.x {
+                break;
+            }
+            ending.pop();
+            active.set_end(slots[other], 0);
+        }
+
+        crossing.clear();
+        active.crossing(rect.y, rect.y_end(), &mut crossing);
+        for other in crossing.iter() {
+            if let Some(area) = rect.intersection(&other.rect()) {
+                let (first, second) = (min(id, other.id), max(id, other.id));
+                overlaps.push(Overlap { first, second, area });
+            }
+        }
+        active.set_end(slots[idx], rect.y_end());
+        ending.push(Reverse((rect.x_end(), idx)));
+    }
+
+    overlaps.sort_unstable_by_key(|o| (o.first, o.second));
+    overlaps
+}
+
+pub fn intact_claims(claims: &[Claim]) -> Vec<u16> {
+    let overlapping = overlapping_pairs(claims)
+        .into_iter()
+        .flat_map(|o| iter::once(o.first).chain(iter::once(o.second)))
+        .collect::<HashSet<_>>();
+    claims.iter().map(|c| c.id).filter(|id| !overlapping.contains(id)).collect()
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Preference {
+    // Topmost, then leftmost position
+    ReadingOrder,
+    // Position whose top-left corner has the least Manhattan distance from the point
+    Nearest(usize, usize),
+}
+
+// Ranges of top-left x coordinates, inclusive, where a claim `width` wide fits in the band
+// `y..y + height` without overlapping the existing claims and without exceeding `max_x`.
+#[allow(dead_code)]
+fn free_columns(claims: &[Claim], y: usize, width: usize, height: usize, max_x: usize) -> Vec<(usize, usize)> {
+    let mut blocked = claims
+        .iter()
+        .filter(|c| c.width != 0 && c.height != 0 && c.y < y + height && y < c.y + c.height)
+        .map(|c| (c.x, c.x + c.width))
+        .collect::<Vec<_>>();
+    blocked.sort_unstable();
+
+    let mut free = Vec::new();
+    let mut cursor = 0;
+    for (start, end) in blocked {
+        if start >= cursor + width {
+            free.push((cursor, start - width));
+        }
+        cursor = max(cursor, end);
+    }
+    free.push((cursor, max_x));
+    free.retain(|&(lo, hi)| lo <= hi && lo <= max_x);
+    free.iter_mut().for_each(|range| range.1 = min(range.1, max_x));
+    free
+}
+
+// Finds where a new `width`x`height` claim can go without overlapping the existing ones,
+// optionally staying within a fabric of size `bounds`. A best position can always be slid
+// towards the preferred one until it touches a claim or the fabric edge, so only those y
+// coordinates need to be tried, and for each of them the best x is found in the free gaps.
+#[allow(dead_code)]
+pub fn find_free_spot(
+    claims: &[Claim],
+    width: usize,
+    height: usize,
+    preference: Preference,
+    bounds: Option<(usize, usize)>,
+) -> Option<Rect> {
+    let (max_x, max_y) = match bounds {
+        Some((fabric_width, fabric_height)) => (fabric_width.checked_sub(width)?, fabric_height.checked_sub(height)?),
+        None => (usize::MAX - width, usize::MAX - height),
+    };
+    let rect_at = |(x, y)| Rect { x, y, width, height };
+
+    if width == 0 || height == 0 {
+        let (x, y) = match preference {
+            Preference::ReadingOrder => (0, 0),
+            Preference::Nearest(x, y) => (min(x, max_x), min(y, max_y)),
+        };
+        return Some(rect_at((x, y)));
+    }
+
+    let mut ys = iter::once(0)
+        .chain(claims.iter().map(|c| c.y + c.height))
+        .collect::<Vec<_>>();
+    if let Preference::Nearest(_, preferred_y) = preference {
+        ys.push(min(preferred_y, max_y));
+        ys.push(max_y);
+        ys.extend(claims.iter().filter_map(|c| c.y.checked_sub(height)));
+    }
+    ys.retain(|&y| y <= max_y);
+    ys.sort_unstable();
+    ys.dedup();
+
+    match preference {
+        Preference::ReadingOrder => ys
+            .into_iter()
+            .find_map(|y| free_columns(claims, y, width, height, max_x).first().map(|&(x, _)| (x, y)))
+            .map(rect_at),
+        Preference::Nearest(preferred_x, preferred_y) => ys
+            .into_iter()
+            .flat_map(|y| {
+                free_columns(claims, y, width, height, max_x)
+                    .into_iter()
+                    .map(move |(lo, hi)| (max(lo, min(preferred_x, hi)), y))
+            })
+            .min_by_key(|&(x, y)| {
+                let distance = max(x, preferred_x) - min(x, preferred_x) + max(y, preferred_y) - min(y, preferred_y);
+                (distance, y, x)
+            })
+            .map(rect_at),
+    }
+}
+
 pub fn input_generator(input: &str) -> Input {
-    let claims: Vec<Claim> = input
+    input
         .lines()
         .map(|line| line.parse::<Claim>().expect("Invalid input"))
-        .collect();
+        .collect()
+}
+
+pub fn part1(claims: &Input) -> usize {
+    overlap_area(claims)
+}
 
-    let (tot_width, tot_height) = claims.iter().fold((0, 0), |(old_w, old_h), claim| {
-        (
-            std::cmp::max(old_w, claim.x + claim.width),
-            std::cmp::max(old_h, claim.y + claim.height),
-        )
-    });
+pub fn part2(claims: &Input) -> u16 {
+    intact_claims(claims)
+        .into_iter()
+        .next()
+        .expect("There's no claim that doesn't overlap")
+}
 
-    let mut claim_count = vec![0; tot_height * tot_width];
+// Unlike the solution this needs the whole fabric in memory
+pub fn visualize(claims: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    if claims.is_empty() {
+        return Ok(());
+    }
+    let width = claims.iter().map(|c| c.x + c.width).max().unwrap_or(0);
+    let height = claims.iter().map(|c| c.y + c.height).max().unwrap_or(0);
 
+    let mut claim_count = Grid { vec: vec![0; width * height], width };
     for claim in claims.iter() {
-        for x in claim.x..(claim.x + claim.width) {
-            for y in claim.y..(claim.y + claim.height) {
-                claim_count[x + y * tot_width] += 1;
+        for y in claim.y..claim.y + claim.height {
+            for x in claim.x..claim.x + claim.width {
+                claim_count[(x, y)] += 1;
             }
         }
     }
 
-    let grid = Grid {
-        vec: claim_count,
-        width: tot_width,
-    };
-
-    (claims, grid)
+    frames.push(&render::from_grid(&claim_count, |&count| match count {
+        0 => Pixel::new('.', Rgb::BLACK),
+        1 => Pixel::new('#', Rgb::GREEN),
+        _ => Pixel::new('X', Rgb::RED),
+    }))
 }
 
-pub fn part1((_, claim_count): &Input) -> usize {
-    claim_count.vec.iter().filter(|&&count| count > 1).count()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    fn example() -> Input {
+        input_generator("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2")
+    }
+
+    #[test]
+    fn example_answers() {
+        let claims = example();
+        assert_eq!(overlap_area(&claims), 4);
+        assert_eq!(intact_claims(&claims), [3]);
+        assert_eq!(
+            overlapping_pairs(&claims),
+            [Overlap { first: 1, second: 2, area: Rect { x: 3, y: 3, width: 2, height: 2 } }]
+        );
+    }
+
+    #[test]
+    fn claims_covering_points() {
+        let claims = example();
+        assert_eq!(claims_at(&claims, 3, 3).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(claims_at(&claims, 6, 6).collect::<Vec<_>>(), [3]);
+        assert_eq!(claims_at(&claims, 7, 7).collect::<Vec<_>>(), []);
+        assert_eq!(claims_at(&claims, 0, 0).collect::<Vec<_>>(), []);
+    }
+
+    #[test]
+    fn overlaps_far_from_the_origin() {
+        let claims = input_generator("#1 @ 1000000000,1000000000: 3x3\n#2 @ 1000000002,1000000002: 2x2\n#3 @ 0,0: 1x1");
+        assert_eq!(overlap_area(&claims), 1);
+        assert_eq!(intact_claims(&claims), [3]);
+    }
+
+    fn claim(id: u16, x: usize, y: usize, width: usize, height: usize) -> Claim {
+        Claim::new(id, Rect { x, y, width, height })
+    }
+
+    #[test]
+    fn overlapping_pairs_match_brute_force() {
+        let mut rng = Lcg::new(5);
+        for count in 0..60 {
+            let claims = (0..count)
+                .map(|id| claim(id as u16, rng.below(30) as usize, rng.below(30) as usize, rng.below(8) as usize, rng.below(8) as usize))
+                .collect::<Vec<_>>();
+            let mut expected = claims
+                .iter()
+                .tuple_combinations()
+                .filter_map(|(a, b)| {
+                    let area = a.rect().intersection(&b.rect())?;
+                    Some(Overlap { first: min(a.id, b.id), second: max(a.id, b.id), area })
+                })
+                .collect::<Vec<_>>();
+            expected.sort_unstable_by_key(|o| (o.first, o.second));
+            assert_eq!(overlapping_pairs(&claims), expected, "{:?}", claims);
+        }
+    }
+
+    #[test]
+    fn stacked_claims_without_overlaps() {
+        // A single column of claims, one row each: every claim crosses the sweep line at once
+        let claims = (0..50_000).map(|id| claim(id as u16, 0, id, 1000, 1)).collect::<Vec<_>>();
+        assert_eq!(overlapping_pairs(&claims), []);
+        assert_eq!(intact_claims(&claims).len(), claims.len());
+        assert_eq!(overlap_area(&claims), 0);
+    }
+
+    // Tries every position in the first `limit` rows and columns
+    fn brute_free_spot(claims: &[Claim], width: usize, height: usize, preference: Preference, bounds: Option<(usize, usize)>) -> Option<Rect> {
+        let limit = 20;
+        let (max_x, max_y) = bounds.map_or((limit, limit), |(w, h)| (w.wrapping_sub(width), h.wrapping_sub(height)));
+        let fits = |rect: &Rect| claims.iter().all(|c| c.rect().intersection(rect).is_none());
+        let spots = (0..=min(max_y, limit))
+            .flat_map(|y| (0..=min(max_x, limit)).map(move |x| Rect { x, y, width, height }))
+            .filter(|rect| fits(rect));
+        match preference {
+            Preference::ReadingOrder => spots.min_by_key(|r| (r.y, r.x)),
+            Preference::Nearest(px, py) => spots.min_by_key(|r| {
+                let distance = max(r.x, px) - min(r.x, px) + max(r.y, py) - min(r.y, py);
+                (distance, r.y, r.x)
+            }),
+        }
+    }
+
+    #[test]
+    fn free_spots_on_the_example() {
+        let claims = example();
+        let rect = |x, y, width, height| Some(Rect { x, y, width, height });
+        assert_eq!(find_free_spot(&claims, 2, 2, Preference::ReadingOrder, None), rect(0, 0, 2, 2));
+        assert_eq!(find_free_spot(&claims, 4, 3, Preference::ReadingOrder, None), rect(7, 0, 4, 3));
+        assert_eq!(find_free_spot(&claims, 4, 3, Preference::ReadingOrder, Some((8, 8))), None);
+        assert_eq!(find_free_spot(&claims, 2, 2, Preference::Nearest(6, 0), None), rect(7, 0, 2, 2));
+        assert_eq!(find_free_spot(&claims, 2, 2, Preference::Nearest(4, 4), None), rect(7, 4, 2, 2));
+        assert_eq!(find_free_spot(&claims, 9, 1, Preference::ReadingOrder, Some((8, 8))), None);
+    }
 
-pub fn part2((claims, claim_count): &Input) -> u16 {
-    'claim: for claim in claims.iter() {
-        for x in claim.x..(claim.x + claim.width) {
-            for y in claim.y..(claim.y + claim.height) {
-                if claim_count.vec[x + y * claim_count.width] > 1 {
-                    continue 'claim;
+    #[test]
+    fn zero_sized_spots() {
+        let claims = example();
+        let rect = |x, y, width, height| Some(Rect { x, y, width, height });
+        assert_eq!(find_free_spot(&claims, 0, 0, Preference::ReadingOrder, None), rect(0, 0, 0, 0));
+        assert_eq!(find_free_spot(&claims, 0, 3, Preference::Nearest(3, 3), None), rect(3, 3, 0, 3));
+        assert_eq!(find_free_spot(&claims, 2, 0, Preference::Nearest(20, 20), Some((8, 8))), rect(6, 8, 2, 0));
+    }
+
+    #[test]
+    fn free_spots_match_brute_force() {
+        let claims = example();
+        let preferences = [
+            Preference::ReadingOrder,
+            Preference::Nearest(0, 0),
+            Preference::Nearest(4, 4),
+            Preference::Nearest(6, 2),
+            Preference::Nearest(3, 9),
+        ];
+        for &preference in preferences.iter() {
+            for &bounds in [None, Some((8, 8)), Some((7, 9))].iter() {
+                for (width, height) in (1..6).cartesian_product(1..6) {
+                    assert_eq!(
+                        find_free_spot(&claims, width, height, preference, bounds),
+                        brute_free_spot(&claims, width, height, preference, bounds),
+                        "{}x{} {:?} {:?}",
+                        width,
+                        height,
+                        preference,
+                        bounds
+                    );
                 }
             }
         }
-        return claim.id;
     }
-
-    panic!("There's no claim that doesn't overlap")
 }
@@ -1,85 +1,573 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
-type Input = HashMap<u16, [u16; 60]>;
-#[derive(Clone, Copy)]
-enum LogEvent {
-    GuardChange(u16),
-    GuardWakesUp(u16),
-    GuardSleeps(u16),
+use std::fmt;
+type Input = Vec<Shift>;
+
+#[derive(FromStr, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[from_str(regex = r"(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})")]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
 }
 
-pub fn input_generator(input: &str) -> Input {
-    let logs = input
-        .lines()
-        .sorted()
-        .map(|line| {
-            if line.contains("Guard") {
-                return LogEvent::GuardChange(
-                    line[26..line.find(" b").expect("Invalid input")]
-                        .parse()
-                        .expect("Invalid input"),
-                );
+impl Date {
+    fn days_in_month(&self) -> u8 {
+        let leap = match (self.year % 4, self.year % 100, self.year % 400) {
+            (_, _, 0) => true,
+            (_, 0, _) => false,
+            (rem, _, _) => rem == 0,
+        };
+        match self.month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month) && self.day >= 1 && self.day <= self.days_in_month()
+    }
+
+    pub fn next(&self) -> Date {
+        match (self.day < self.days_in_month(), self.month < 12) {
+            (true, _) => Date { day: self.day + 1, ..*self },
+            (false, true) => Date { month: self.month + 1, day: 1, ..*self },
+            (false, false) => Date { year: self.year + 1, month: 1, day: 1 },
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(FromStr, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[from_str(regex = r"(?P<date>\d{4}-\d{2}-\d{2}) (?P<hour>\d{2}):(?P<minute>\d{2})")]
+pub struct DateTime {
+    pub date: Date,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:02}:{:02}", self.date, self.hour, self.minute)
+    }
+}
+
+#[derive(Display, FromStr, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    #[display("Guard #{0} begins shift")]
+    BeginsShift(u16),
+    #[display("falls asleep")]
+    FallsAsleep,
+    #[display("wakes up")]
+    WakesUp,
+}
+
+#[derive(FromStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[from_str(regex = r"^\[(?P<time>[^\]]+)\] (?P<event>.+)$")]
+pub struct LogEntry {
+    pub time: DateTime,
+    pub event: Event,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LogError {
+    InvalidLine { line: usize, text: String },
+    InvalidTime { line: usize, time: DateTime },
+    EventWithoutShift { line: usize, time: DateTime, event: Event },
+    // `line` is where the guard falls asleep
+    SleepWithoutWake { guard: u16, line: usize, since: DateTime },
+    WakeWithoutSleep { guard: u16, line: usize, time: DateTime },
+    OutsideMidnightHour { guard: u16, line: usize, time: DateTime, event: Event },
+    // `line` is where the second guard begins the shift
+    OverlappingShifts { date: Date, first: u16, second: u16, line: usize },
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogError::InvalidLine { line, text } => write!(f, "line {}: can't parse {:?}", line, text),
+            LogError::InvalidTime { line, time } => write!(f, "line {}: {} is not a valid time", line, time),
+            LogError::EventWithoutShift { line, time, event } => {
+                write!(f, "line {}: [{}] {:?} happens before any guard begins a shift", line, time, event.to_string())
             }
-            let minute: u16 = line[15..17].parse().expect("Invalid input");
-            if line.contains("falls") {
-                return LogEvent::GuardSleeps(minute);
+            LogError::SleepWithoutWake { guard, line, since } => {
+                write!(f, "line {}: guard #{} falls asleep at {} and never wakes up", line, guard, since)
             }
-            if line.contains("wakes") {
-                return LogEvent::GuardWakesUp(minute);
+            LogError::WakeWithoutSleep { guard, line, time } => {
+                write!(f, "line {}: guard #{} wakes up at {} without falling asleep", line, guard, time)
             }
-            panic!("Invalid input")
-        })
-        .collect::<Vec<_>>();
-
-    let mut current_id = match logs[0] {
-        LogEvent::GuardChange(id) => id,
-        _ => panic!("First event is not GuardChange"),
-    };
-    let mut asleep_minute = match logs[1] {
-        LogEvent::GuardSleeps(minute) => minute,
-        _ => panic!("Second event is not GuardSleeps"),
-    };
-
-    let mut guard_map = HashMap::new();
-    for &log in &logs[2..] {
-        match log {
-            LogEvent::GuardChange(id) => current_id = id,
-            LogEvent::GuardSleeps(minute) => asleep_minute = minute,
-            LogEvent::GuardWakesUp(minute) => {
-                for i in asleep_minute..minute {
-                    guard_map.entry(current_id).or_insert([0u16; 60])[i as usize] += 1;
+            LogError::OutsideMidnightHour { guard, line, time, event } => {
+                write!(f, "line {}: guard #{} {} at {}, outside the midnight hour of the shift", line, guard, event, time)
+            }
+            LogError::OverlappingShifts { date, first, second, line } => {
+                write!(f, "line {}: guards #{} and #{} both have a shift on {}", line, first, second, date)
+            }
+        }
+    }
+}
+
+// A guard's shift, covering the midnight hour of `date`. Shifts beginning before midnight
+// belong to the following day.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Shift {
+    pub guard: u16,
+    pub begins: DateTime,
+    pub date: Date,
+    // Half open ranges of minutes spent asleep, in order
+    pub naps: Vec<(u8, u8)>,
+}
+
+impl Shift {
+    fn new(guard: u16, begins: DateTime) -> Self {
+        let date = if begins.hour == 0 { begins.date } else { begins.date.next() };
+        Self { guard, begins, date, naps: Vec::new() }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_asleep(&self, minute: u8) -> bool {
+        self.naps.iter().any(|&(from, to)| from <= minute && minute < to)
+    }
+
+    #[allow(dead_code)]
+    pub fn minutes_asleep(&self) -> u32 {
+        self.naps.iter().map(|&(from, to)| (to - from) as u32).sum()
+    }
+}
+
+// The entries in chronological order, each with its line number
+pub fn parse_entries(input: &str) -> Result<Vec<(usize, LogEntry)>, LogError> {
+    let mut entries = Vec::new();
+    for (idx, line) in input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let entry = line
+            .trim()
+            .parse::<LogEntry>()
+            .map_err(|_| LogError::InvalidLine { line: idx + 1, text: line.to_string() })?;
+        if !entry.time.date.is_valid() || entry.time.hour > 23 || entry.time.minute > 59 {
+            return Err(LogError::InvalidTime { line: idx + 1, time: entry.time });
+        }
+        entries.push((idx + 1, entry));
+    }
+    // The log isn't in chronological order, entries with the same time keep their order
+    entries.sort_by_key(|(_, entry)| entry.time);
+    Ok(entries)
+}
+
+pub fn parse_shifts(input: &str) -> Result<Vec<Shift>, LogError> {
+    let mut shifts = Vec::<Shift>::new();
+    // Line and time the current guard fell asleep at
+    let mut asleep_since = None;
+
+    for (line, entry) in parse_entries(input)? {
+        let shift = shifts.last_mut();
+        match (entry.event, shift) {
+            (Event::BeginsShift(guard), shift) => {
+                if let (Some(shift), Some((line, since))) = (&shift, asleep_since) {
+                    return Err(LogError::SleepWithoutWake { guard: shift.guard, line, since });
+                }
+                let new = Shift::new(guard, entry.time);
+                if let Some(shift) = shift.filter(|shift| shift.date == new.date) {
+                    return Err(LogError::OverlappingShifts { date: new.date, first: shift.guard, second: guard, line });
+                }
+                shifts.push(new);
+            }
+            (event, None) => return Err(LogError::EventWithoutShift { line, time: entry.time, event }),
+            (event, Some(shift)) if entry.time.date != shift.date || entry.time.hour != 0 => {
+                return Err(LogError::OutsideMidnightHour { guard: shift.guard, line, time: entry.time, event });
+            }
+            (Event::FallsAsleep, Some(shift)) => {
+                if let Some((line, since)) = asleep_since {
+                    return Err(LogError::SleepWithoutWake { guard: shift.guard, line, since });
                 }
+                asleep_since = Some((line, entry.time));
             }
+            (Event::WakesUp, Some(shift)) => match asleep_since.take() {
+                Some((_, since)) => shift.naps.push((since.minute, entry.time.minute)),
+                None => return Err(LogError::WakeWithoutSleep { guard: shift.guard, line, time: entry.time }),
+            },
         }
     }
 
+    match (shifts.last(), asleep_since) {
+        (Some(shift), Some((line, since))) => Err(LogError::SleepWithoutWake { guard: shift.guard, line, since }),
+        _ => Ok(shifts),
+    }
+}
+
+// How many times each guard was asleep at each minute of the midnight hour
+pub fn minutes_by_guard(shifts: &[Shift]) -> BTreeMap<u16, [u16; 60]> {
+    let mut guard_map = BTreeMap::new();
+    for shift in shifts {
+        let minutes = guard_map.entry(shift.guard).or_insert([0u16; 60]);
+        for &(from, to) in shift.naps.iter() {
+            for minute in from..to {
+                minutes[minute as usize] += 1;
+            }
+        }
+    }
     guard_map
 }
 
-pub fn part1(guard_map: &Input) -> u32 {
-    let (&id, _) = guard_map
-        .iter()
-        .max_by_key(|(_, h)| h.iter().sum::<u16>())
-        .expect("No max guard found");
-    let (minute, _) = guard_map[&id]
-        .iter()
-        .enumerate()
-        .max_by_key(|&(_, &m)| m)
-        .expect("No max hour found");
-
-    minute as u32 * id as u32
-}
-
-pub fn part2(guard_map: &Input) -> u32 {
-    let (id, (minute, _)) = guard_map
-        .iter()
-        .map(|(&id, minutes)| {
-            (
-                id,
-                minutes.iter().enumerate().max_by_key(|(_, &m)| m).unwrap(),
-            )
-        })
-        .max_by_key(|(_, (_, &m))| m)
-        .expect("No max hour found");
-    id as u32 * minute as u32
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GuardSummary {
+    pub guard: u16,
+    pub shifts: usize,
+    pub minutes_asleep: u32,
+    pub histogram: [u16; 60],
+}
+
+impl GuardSummary {
+    // All the minutes the guard was asleep the most times at, with that count
+    pub fn most_slept_minutes(&self) -> (Vec<u8>, u16) {
+        let count = self.histogram.iter().copied().max().unwrap_or(0);
+        if count == 0 {
+            return (Vec::new(), 0);
+        }
+        let minutes = (0..60u8).filter(|&minute| self.histogram[minute as usize] == count).collect();
+        (minutes, count)
+    }
+
+    fn histogram_row(&self) -> String {
+        self.histogram
+            .iter()
+            .map(|&count| match count {
+                0 => '.',
+                1..=9 => (b'0' + count as u8) as char,
+                _ => '+',
+            })
+            .collect()
+    }
+}
+
+// Sleep statistics of every guard, ordered by guard id
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SleepReport {
+    pub guards: Vec<GuardSummary>,
+}
+
+impl SleepReport {
+    pub fn new(shifts: &[Shift]) -> Self {
+        let mut shift_counts = BTreeMap::new();
+        for shift in shifts {
+            *shift_counts.entry(shift.guard).or_insert(0) += 1;
+        }
+        let guards = minutes_by_guard(shifts)
+            .into_iter()
+            .map(|(guard, histogram)| GuardSummary {
+                guard,
+                shifts: shift_counts[&guard],
+                minutes_asleep: histogram.iter().map(|&count| count as u32).sum(),
+                histogram,
+            })
+            .collect();
+        Self { guards }
+    }
+
+    // Guards who slept the most minutes, more than one in case of a tie
+    pub fn sleepiest_guards(&self) -> Vec<&GuardSummary> {
+        let most = self.guards.iter().map(|g| g.minutes_asleep).max();
+        self.guards.iter().filter(|g| Some(g.minutes_asleep) == most).collect()
+    }
+
+    // Guards who were asleep the most times at the same minute, with their minutes
+    pub fn most_regular_guards(&self) -> Vec<(&GuardSummary, Vec<u8>)> {
+        let most = self.guards.iter().map(|g| g.most_slept_minutes().1).max();
+        self.guards
+            .iter()
+            .map(|g| (g, g.most_slept_minutes()))
+            .filter(|(_, (_, count))| Some(*count) == most)
+            .map(|(g, (minutes, _))| (g, minutes))
+            .collect()
+    }
+
+    // One row per guard, the minutes in `most_slept_minutes` are separated by `;`
+    #[allow(dead_code)]
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("guard,shifts,minutes_asleep,most_slept_minutes,most_slept_count");
+        for minute in 0..60 {
+            csv += &format!(",m{:02}", minute);
+        }
+        csv.push('\n');
+        for g in self.guards.iter() {
+            let (minutes, count) = g.most_slept_minutes();
+            csv += &format!(
+                "{},{},{},{},{}",
+                g.guard,
+                g.shifts,
+                g.minutes_asleep,
+                minutes.iter().join(";"),
+                count
+            );
+            for count in g.histogram.iter() {
+                csv += &format!(",{}", count);
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+// Text table, the histogram has one column per minute with the number of times
+// the guard was asleep at it, `+` meaning 10 or more.
+impl fmt::Display for SleepReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tens = (0..60).map(|minute| if minute % 10 == 0 { (b'0' + minute / 10) as char } else { ' ' }).collect();
+        let header = ["Guard", "Shifts", "Asleep", "Minutes", "Count"].iter().map(|s| s.to_string()).chain(iter::once(tens));
+        let rows = iter::once(header.collect::<Vec<_>>())
+            .chain(self.guards.iter().map(|g| {
+                let (minutes, count) = g.most_slept_minutes();
+                let minutes = if minutes.is_empty() { "-".to_string() } else { minutes.iter().join(",") };
+                vec![
+                    format!("#{}", g.guard),
+                    g.shifts.to_string(),
+                    g.minutes_asleep.to_string(),
+                    minutes,
+                    count.to_string(),
+                    g.histogram_row(),
+                ]
+            }))
+            .collect::<Vec<_>>();
+        let widths = (0..5).map(|col| rows.iter().map(|row| row[col].len()).max().unwrap_or(0)).collect::<Vec<_>>();
+
+        for row in rows.iter() {
+            let mut line = String::new();
+            for (col, (cell, width)) in row.iter().zip(widths.iter()).enumerate() {
+                // Guard and minutes are left aligned, the numbers right aligned
+                match col {
+                    0 | 3 => line += &format!("{:<width$}  ", cell, width = width),
+                    _ => line += &format!("{:>width$}  ", cell, width = width),
+                }
+            }
+            line += &row[5];
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn input_generator(input: &str) -> Input {
+    parse_shifts(input).unwrap_or_else(|error| panic!("Invalid input: {}", error))
+}
+
+// Ties are broken in favour of the lowest guard id and the earliest minute
+pub fn part1(shifts: &Input) -> u32 {
+    let report = SleepReport::new(shifts);
+    let guard = *report.sleepiest_guards().first().expect("No max guard found");
+    let (minutes, _) = guard.most_slept_minutes();
+    let minute = *minutes.first().expect("No max hour found");
+
+    minute as u32 * guard.guard as u32
+}
+
+pub fn part2(shifts: &Input) -> u32 {
+    let report = SleepReport::new(shifts);
+    let (guard, minutes) = report.most_regular_guards().into_iter().next().expect("No max hour found");
+    guard.guard as u32 * minutes[0] as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    const EXAMPLE: &str = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-01 00:30] falls asleep
+[1518-11-01 00:55] wakes up
+[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:50] wakes up
+[1518-11-03 00:05] Guard #10 begins shift
+[1518-11-03 00:24] falls asleep
+[1518-11-03 00:29] wakes up
+[1518-11-04 00:02] Guard #99 begins shift
+[1518-11-04 00:36] falls asleep
+[1518-11-04 00:46] wakes up
+[1518-11-05 00:03] Guard #99 begins shift
+[1518-11-05 00:45] falls asleep
+[1518-11-05 00:55] wakes up";
+
+    #[test]
+    fn example_answers() {
+        let shifts = input_generator(EXAMPLE);
+        assert_eq!(part1(&shifts), 240);
+        assert_eq!(part2(&shifts), 4455);
+    }
+
+    #[test]
+    fn report_csv() {
+        let csv = SleepReport::new(&input_generator(EXAMPLE)).to_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+
+        let minutes = (0..60).map(|minute| format!("m{:02}", minute)).join(",");
+        assert_eq!(lines[0], format!("guard,shifts,minutes_asleep,most_slept_minutes,most_slept_count,{}", minutes));
+
+        let histogram = (0..60).map(|minute| match minute {
+            24 => 2,
+            5..=23 | 25..=28 | 30..=54 => 1,
+            _ => 0,
+        });
+        assert_eq!(lines[1], format!("10,2,50,24,2,{}", histogram.format(",")));
+        assert!(lines[2].starts_with("99,3,30,45,3,"));
+    }
+
+    #[test]
+    fn ties_are_listed() {
+        let log = "\
+[1518-03-01 00:00] Guard #7 begins shift
+[1518-03-01 00:10] falls asleep
+[1518-03-01 00:12] wakes up
+[1518-03-02 23:50] Guard #7 begins shift
+[1518-03-03 00:30] falls asleep
+[1518-03-03 00:31] wakes up
+[1518-03-04 00:00] Guard #8 begins shift";
+        let report = SleepReport::new(&input_generator(log));
+        assert_eq!(report.guards[0].most_slept_minutes(), (vec![10, 11, 30], 1));
+        // A guard who never slept has no most slept minute
+        assert_eq!(report.guards[1].most_slept_minutes(), (vec![], 0));
+
+        let csv = report.to_csv();
+        let rows = csv.lines().skip(1).map(|line| line.split(',').take(5).join(",")).collect::<Vec<_>>();
+        assert_eq!(rows, ["7,2,3,10;11;30,1", "8,1,0,,0"]);
+    }
+
+    #[test]
+    fn report_table() {
+        let table = SleepReport::new(&input_generator(EXAMPLE)).to_string();
+        assert_eq!(
+            table.lines().collect::<Vec<_>>(),
+            [
+                "Guard  Shifts  Asleep  Minutes  Count  0         1         2         3         4         5",
+                "#10         2      50  24           2  .....111111111111111111121111.1111111111111111111111111.....",
+                "#99         3      30  45           3  ....................................1111222223222211111.....",
+            ]
+        );
+    }
+
+    fn time(text: &str) -> DateTime {
+        text.parse().unwrap()
+    }
+
+    fn error(log: &str) -> LogError {
+        parse_shifts(log).unwrap_err()
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let log = "[1518-11-01 00:00] Guard #10 begins shift\n\n[1518-11-01 00:05] dozes off";
+        assert_eq!(error(log), LogError::InvalidLine { line: 3, text: "[1518-11-01 00:05] dozes off".to_string() });
+        assert_eq!(error(log).to_string(), "line 3: can't parse \"[1518-11-01 00:05] dozes off\"");
+        let log = "[1518-11-1 00:05] falls asleep";
+        assert_eq!(error(log), LogError::InvalidLine { line: 1, text: log.to_string() });
+    }
+
+    #[test]
+    fn invalid_times() {
+        // 1518 isn't a leap year
+        let log = "[1518-02-28 23:58] Guard #10 begins shift\n[1518-02-29 00:05] falls asleep";
+        assert_eq!(error(log), LogError::InvalidTime { line: 2, time: time("1518-02-29 00:05") });
+        let log = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:60] wakes up";
+        assert_eq!(error(log), LogError::InvalidTime { line: 3, time: time("1518-11-01 00:60") });
+        assert_eq!(error("[1518-13-01 00:00] Guard #10 begins shift"), LogError::InvalidTime { line: 1, time: time("1518-13-01 00:00") });
+    }
+
+    #[test]
+    fn event_without_shift() {
+        let log = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-10-31 23:50] falls asleep";
+        assert_eq!(
+            error(log),
+            LogError::EventWithoutShift { line: 2, time: time("1518-10-31 23:50"), event: Event::FallsAsleep }
+        );
+        assert_eq!(
+            error(log).to_string(),
+            "line 2: [1518-10-31 23:50] \"falls asleep\" happens before any guard begins a shift"
+        );
+    }
+
+    #[test]
+    fn sleep_without_wake() {
+        // Another guard's shift begins while the guard sleeps
+        let log = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 23:58] Guard #99 begins shift";
+        assert_eq!(error(log), LogError::SleepWithoutWake { guard: 10, line: 2, since: time("1518-11-01 00:05") });
+
+        // The log ends while the guard sleeps
+        let log = "[1518-11-01 00:05] falls asleep\n[1518-11-01 00:00] Guard #10 begins shift";
+        assert_eq!(error(log), LogError::SleepWithoutWake { guard: 10, line: 1, since: time("1518-11-01 00:05") });
+
+        // The guard falls asleep twice
+        let log = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:10] falls asleep
+[1518-11-01 00:05] falls asleep";
+        assert_eq!(error(log), LogError::SleepWithoutWake { guard: 10, line: 3, since: time("1518-11-01 00:05") });
+    }
+
+    #[test]
+    fn wake_without_sleep() {
+        let log = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:30] falls asleep
+[1518-11-01 00:25] wakes up";
+        assert_eq!(error(log), LogError::WakeWithoutSleep { guard: 10, line: 3, time: time("1518-11-01 00:25") });
+    }
+
+    #[test]
+    fn outside_midnight_hour() {
+        // The shift begins before midnight, so it's the one of the next day
+        let log = "[1518-11-01 23:58] Guard #99 begins shift\n[1518-11-01 23:59] falls asleep";
+        assert_eq!(
+            error(log),
+            LogError::OutsideMidnightHour { guard: 99, line: 2, time: time("1518-11-01 23:59"), event: Event::FallsAsleep }
+        );
+        let log = "\
+[1518-11-02 00:40] falls asleep
+[1518-11-02 01:05] wakes up
+[1518-11-01 23:58] Guard #99 begins shift";
+        assert_eq!(
+            error(log),
+            LogError::OutsideMidnightHour { guard: 99, line: 2, time: time("1518-11-02 01:05"), event: Event::WakesUp }
+        );
+    }
+
+    #[test]
+    fn overlapping_shifts() {
+        // Both shifts are on 11-01, the one starting the day before comes first
+        let log = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-10-31 23:58] Guard #99 begins shift";
+        let date = Date { year: 1518, month: 11, day: 1 };
+        assert_eq!(error(log), LogError::OverlappingShifts { date, first: 99, second: 10, line: 1 });
+        assert_eq!(error(log).to_string(), "line 1: guards #99 and #10 both have a shift on 1518-11-01");
+    }
+
+    #[test]
+    fn shuffled_logs() {
+        let expected = parse_shifts(EXAMPLE).unwrap();
+        let mut lines = EXAMPLE.lines().collect::<Vec<_>>();
+        let mut rng = Lcg::new(11);
+        for _ in 0..20 {
+            for i in (1..lines.len()).rev() {
+                lines.swap(i, rng.below(i as u32 + 1) as usize);
+            }
+            assert_eq!(parse_shifts(&lines.join("\n")), Ok(expected.clone()));
+        }
+
+        // Errors point at the lines of the shuffled log
+        lines.retain(|line| *line != "[1518-11-04 00:46] wakes up");
+        let line = lines.iter().position(|line| *line == "[1518-11-04 00:36] falls asleep").unwrap() + 1;
+        assert_eq!(
+            parse_shifts(&lines.join("\n")),
+            Err(LogError::SleepWithoutWake { guard: 99, line, since: time("1518-11-04 00:36") })
+        );
+    }
 }
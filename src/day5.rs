@@ -1,43 +1,285 @@
 #![allow(unused_imports, unused_variables)]
+use super::json::Json;
 use super::prelude::*;
-type Input = String;
+use super::runner::Answer;
+type Input = Vec<char>;
 
-pub fn input_generator(input: &str) -> Input { input.to_string() }
+pub fn input_generator(input: &str) -> Input { input.trim().chars().collect() }
 
-fn collapse(input: impl Iterator<Item = char>) -> usize {
-    fn are_opposite(c1: char, c2: char) -> bool {
-        return c1 != c2 && c1.to_ascii_lowercase() == c2.to_ascii_lowercase();
+// Length of the polymer pieces reduced sequentially by each task
+const CHUNK_SIZE: usize = 1 << 14;
+
+// Which adjacent pairs of units react, and what they leave behind
+#[derive(Clone, Debug)]
+pub struct Reactions {
+    opposite_case: bool,
+    rules: HashMap<(char, char), Option<char>>,
+}
+
+impl Default for Reactions {
+    fn default() -> Self { Self::opposite_case() }
+}
+
+// The only char of a case mapping, None when it maps to several chars
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let first = chars.next();
+    if chars.next().is_some() { None } else { first }
+}
+
+// `lower` is a lowercase letter, `upper` is its uppercase form and maps back to it. This
+// gives every letter at most one partner: titlecase letters like `ǅ` react with nothing,
+// and neither do letters like the Kelvin sign, whose lowercase `k` maps back to ASCII `K`.
+fn case_pair(lower: char, upper: char) -> bool {
+    lower.is_lowercase()
+        && upper.is_uppercase()
+        && single(lower.to_uppercase()) == Some(upper)
+        && single(upper.to_lowercase()) == Some(lower)
+}
+
+fn opposite_case(c1: char, c2: char) -> bool {
+    if c1.is_ascii() && c2.is_ascii() {
+        c1 != c2 && c1.eq_ignore_ascii_case(&c2)
+    } else {
+        case_pair(c1, c2) || case_pair(c2, c1)
     }
+}
 
-    let mut stack = Vec::new();
+impl Reactions {
+    // No unit reacts
+    #[allow(dead_code)]
+    pub fn none() -> Self {
+        Self { opposite_case: false, rules: HashMap::new() }
+    }
 
-    for c in input {
-        match stack.last() {
-            Some(&last) if are_opposite(c, last) => {
-                stack.pop();
-            }
-            _ => {
-                stack.push(c);
+    // A lowercase letter and its uppercase form annihilate, in any alphabet
+    pub fn opposite_case() -> Self {
+        Self { opposite_case: true, rules: HashMap::new() }
+    }
+
+    // `first` and `second` annihilate when adjacent, in either order
+    #[allow(dead_code)]
+    pub fn annihilating(mut self, first: char, second: char) -> Self {
+        self.rules.insert((first, second), None);
+        self.rules.insert((second, first), None);
+        self
+    }
+
+    // `first` followed by `second` becomes `product`, which may react again
+    #[allow(dead_code)]
+    pub fn replacing(mut self, first: char, second: char, product: char) -> Self {
+        self.rules.insert((first, second), Some(product));
+        self
+    }
+
+    // None if the units don't react, otherwise what's left of them
+    pub fn react(&self, first: char, second: char) -> Option<Option<char>> {
+        match self.rules.get(&(first, second)) {
+            Some(&product) => Some(product),
+            None if self.opposite_case && opposite_case(first, second) => Some(None),
+            None => None,
+        }
+    }
+
+    // Units are of the same type regardless of their case
+    pub fn unit_type(&self, unit: char) -> char {
+        unit.to_lowercase().next().unwrap_or(unit)
+    }
+
+    // When every unit annihilates with at most one other unit, which annihilates with it,
+    // the result doesn't depend on the order of the reactions: polymers can be split and
+    // reduced independently, and reducing before removing units doesn't change the outcome.
+    fn is_confluent(&self) -> bool {
+        if self.opposite_case {
+            return self.rules.is_empty();
+        }
+        let mut partners = HashMap::new();
+        self.rules.iter().all(|(&(first, second), product)| {
+            product.is_none()
+                && *partners.entry(first).or_insert(second) == second
+                && self.rules.get(&(second, first)) == Some(&None)
+        })
+    }
+
+    fn collapse(&self, input: impl Iterator<Item = char>) -> Vec<char> {
+        let mut stack = Vec::new();
+        for c in input {
+            self.push(&mut stack, c);
+        }
+        stack
+    }
+
+    fn push(&self, stack: &mut Vec<char>, mut unit: char) {
+        while let Some(&last) = stack.last() {
+            match self.react(last, unit) {
+                Some(None) => {
+                    stack.pop();
+                    return;
+                }
+                Some(Some(product)) => {
+                    stack.pop();
+                    unit = product;
+                }
+                None => break,
             }
         }
+        stack.push(unit);
+    }
+
+    // Two reduced polymers can only react where they're joined, so they're combined by
+    // cancelling units across the boundary. This makes the reduction associative.
+    fn combine(&self, mut left: Vec<char>, right: Vec<char>) -> Vec<char> {
+        let reacting = left
+            .iter()
+            .rev()
+            .zip(right.iter())
+            .take_while(|&(&l, &r)| self.react(l, r) == Some(None))
+            .count();
+        left.truncate(left.len() - reacting);
+        left.extend_from_slice(&right[reacting..]);
+        left
+    }
+
+    // Reactions happen left to right, when they are confluent the polymer is reduced in parallel
+    pub fn reduce(&self, polymer: &[char]) -> Vec<char> {
+        if !self.is_confluent() {
+            return self.collapse(polymer.iter().copied());
+        }
+        polymer
+            .par_chunks(CHUNK_SIZE)
+            .map(|chunk| self.collapse(chunk.iter().copied()))
+            .reduce(Vec::new, |left, right| self.combine(left, right))
+    }
+
+    // Removes all the units of each type in turn, returning the shortest reduced polymer.
+    // Ties go to the type that comes first.
+    pub fn best_removal(&self, polymer: &[char]) -> Option<Removal> {
+        let reduced;
+        let polymer = if self.is_confluent() {
+            reduced = self.reduce(polymer);
+            &reduced[..]
+        } else {
+            polymer
+        };
+
+        let mut units = polymer.iter().map(|&c| self.unit_type(c)).unique().collect::<Vec<_>>();
+        units.sort_unstable();
+        units
+            .par_iter()
+            .map(|&unit| {
+                let rest = polymer.iter().copied().filter(|&c| self.unit_type(c) != unit).collect::<Vec<_>>();
+                Removal { unit, polymer: self.reduce(&rest) }
+            })
+            .min_by_key(|removal| (removal.polymer.len(), removal.unit))
     }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Removal {
+    pub unit: char,
+    pub polymer: Vec<char>,
+}
 
-    stack.len()
+impl Answer for Removal {
+    fn text(&self) -> String { self.polymer.len().to_string() }
+    fn value(&self) -> Json {
+        Json::object(vec![
+            ("length", Json::Int(self.polymer.len() as i128)),
+            ("unit", Json::Str(self.unit.to_string())),
+        ])
+    }
 }
 
 pub fn part1(input: &Input) -> usize {
-    collapse(input.chars())
+    Reactions::opposite_case().reduce(input).len()
 }
 
-pub fn part2(input: &Input) -> usize {
-    input
-        .chars()
-        .map(|c| c.to_ascii_lowercase())
-        .unique()
-        .map(|c_lower| {
-            let c_upper = c_lower.to_ascii_uppercase();
-            collapse(input.chars().filter(|&x| x != c_lower && x != c_upper))
-        })
-        .min()
-        .unwrap()
+pub fn part2(input: &Input) -> Option<Removal> {
+    Reactions::opposite_case().best_removal(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    fn chars(polymer: &str) -> Vec<char> {
+        polymer.chars().collect()
+    }
+
+    #[test]
+    fn example() {
+        let polymer = input_generator("dabAcCaCBAcCcaDA\n");
+        assert_eq!(Reactions::default().reduce(&polymer), chars("dabCBAcaDA"));
+        assert_eq!(part1(&polymer), 10);
+        assert_eq!(part2(&polymer), Some(Removal { unit: 'c', polymer: chars("daDA") }));
+    }
+
+    #[test]
+    fn opposite_case_beyond_ascii() {
+        let reactions = Reactions::opposite_case();
+        assert_eq!(reactions.reduce(&chars("aÉéäbBÄΣσΩ")), chars("aΩ"));
+        assert_eq!(reactions.unit_type('Ä'), 'ä');
+        // Same case doesn't react
+        assert_eq!(reactions.reduce(&chars("ééÄÄ")), chars("ééÄÄ"));
+    }
+
+    #[test]
+    fn letters_with_several_case_partners() {
+        let reactions = Reactions::opposite_case();
+        // The titlecase ǅ reacts with neither ǆ nor Ǆ, which react with each other
+        assert_eq!(reactions.reduce(&chars("Ǆǅǆ")), chars("Ǆǅǆ"));
+        assert_eq!(reactions.reduce(&chars("ǅǆǄǅ")), chars("ǅǅ"));
+        // The Kelvin sign lowercases to k, but k uppercases to the ASCII K
+        assert_eq!(reactions.reduce(&chars("\u{212A}k")), chars("\u{212A}k"));
+        assert_eq!(reactions.reduce(&chars("K\u{212A}Kk")), chars("K\u{212A}"));
+        // The final sigma uppercases to Σ, which lowercases to σ
+        assert_eq!(reactions.reduce(&chars("ςΣσ")), chars("ς"));
+    }
+
+    #[test]
+    fn chunk_boundaries_between_case_partners() {
+        let reactions = Reactions::opposite_case();
+        for &tail in ["Ǆ|ǅǆ", "ǆ|ǅǄ", "\u{212A}|kK", "k|\u{212A}K"].iter() {
+            let (left, right) = tail.split_at(tail.find('|').unwrap());
+            let mut polymer = vec!['q'; CHUNK_SIZE - left.chars().count()];
+            polymer.extend(left.chars());
+            polymer.extend(right[1..].chars());
+            assert_eq!(reactions.reduce(&polymer), reactions.collapse(polymer.iter().copied()), "{}", tail);
+        }
+    }
+
+    #[test]
+    fn custom_annihilations() {
+        let reactions = Reactions::none().annihilating('(', ')').annihilating('<', '>');
+        assert_eq!(reactions.reduce(&chars("(()<>)x)(<")), chars("x<"));
+        assert_eq!(Reactions::none().reduce(&chars("aAbB")), chars("aAbB"));
+    }
+
+    #[test]
+    fn replacements_cascade() {
+        let reactions = Reactions::none().replacing('A', 'b', 'C').replacing('C', 'd', 'E').annihilating('x', 'E');
+        // A + b = C, C + d = E, then E annihilates with the x before it
+        assert_eq!(reactions.reduce(&chars("zxAbdy")), chars("zy"));
+        // Replacements only apply in the given order
+        assert_eq!(reactions.reduce(&chars("bA")), chars("bA"));
+    }
+
+    #[test]
+    fn replacements_across_chunks() {
+        // Reducing chunks separately would leave the A and b on either side of the boundary
+        let reactions = Reactions::none().replacing('A', 'b', 'C');
+        let mut polymer = vec!['q'; CHUNK_SIZE - 1];
+        polymer.extend(chars("Abz"));
+        let mut expected = vec!['q'; CHUNK_SIZE - 1];
+        expected.extend(chars("Cz"));
+        assert_eq!(reactions.reduce(&polymer), expected);
+    }
+
+    #[test]
+    fn parallel_reduction_matches_sequential() {
+        let mut rng = Lcg::new(7);
+        let polymer = (0..5 * CHUNK_SIZE + 123).map(|_| ['a', 'A', 'b', 'B'][rng.below(4) as usize]).collect::<Vec<_>>();
+        let reactions = Reactions::default();
+        assert_eq!(reactions.reduce(&polymer), reactions.collapse(polymer.iter().copied()));
+    }
 }
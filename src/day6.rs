@@ -1,33 +1,363 @@
 #![allow(unused_imports, unused_variables)]
+use super::grid_ext::GridExt;
 use super::prelude::*;
+use super::render::{Frames, Pixel, Render, Rgb};
 type Input = Vec<Point>;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, FromStr)]
-#[from_str(regex = r"(?P<x>\d+), (?P<y>\d+)")]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, FromStr, Debug)]
+#[from_str(regex = r"(?P<x>-?\d+), (?P<y>-?\d+)")]
 pub struct Point {
-    x: usize,
-    y: usize,
+    pub x: i64,
+    pub y: i64,
 }
 
-impl Point {
-    fn dist(&self, x: usize, y: usize) -> usize {
-        return ((self.x as i16 - x as i16).abs() + (self.y as i16 - y as i16).abs()) as usize;
+fn abs_diff(a: i64, b: i64) -> u128 {
+    (a as i128 - b as i128).unsigned_abs()
+}
+
+pub trait Metric: Sync {
+    fn distance(&self, a: Point, b: Point) -> u128;
+
+    // Distance between locations differing by `d` along a single axis
+    fn axis_distance(&self, d: u64) -> u128 {
+        self.distance(Point { x: 0, y: 0 }, Point { x: d as i64, y: 0 })
+    }
+
+    // How far past the bounding box of the points the areas are mapped
+    #[allow(dead_code)]
+    fn margin(&self, width: u64, height: u64) -> u64 {
+        max(width, height)
+    }
+
+    // Which points own infinitely many locations, given the owners of the mapped box. None
+    // when the metric can't tell.
+    fn infinite_areas(&self, points: &[Point], owners: &Grid<Owner>) -> Option<Vec<bool>> {
+        None
+    }
+
+    // Total distance from each coordinate in `from..=to` to the given ones along one axis,
+    // for metrics where the distance is the sum of the distances along each axis
+    fn axis_totals(&self, coords: &[i64], from: i64, to: i64) -> Option<Vec<u128>> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Manhattan;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub struct Chebyshev;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub struct SquaredEuclidean;
+
+impl Metric for Manhattan {
+    fn distance(&self, a: Point, b: Point) -> u128 {
+        abs_diff(a.x, b.x) + abs_diff(a.y, b.y)
+    }
+
+    // See `AreaMap::new`
+    fn margin(&self, width: u64, height: u64) -> u64 { 0 }
+
+    fn infinite_areas(&self, points: &[Point], owners: &Grid<Owner>) -> Option<Vec<bool>> {
+        Some(touching_boundary(owners, points.len()))
+    }
+
+    // With the coordinates sorted, those below `c` are the ones before the partition point
+    // and contribute `c - coord`, the rest `coord - c`, so prefix sums give each total in O(log N).
+    fn axis_totals(&self, coords: &[i64], from: i64, to: i64) -> Option<Vec<u128>> {
+        let mut coords = coords.to_vec();
+        coords.sort_unstable();
+        let prefix = iter::once(0)
+            .chain(coords.iter().scan(0, |sum, &c| {
+                *sum += c as i128;
+                Some(*sum)
+            }))
+            .collect::<Vec<i128>>();
+        let (n, total) = (coords.len() as i128, prefix[coords.len()]);
+
+        let totals = (from..=to)
+            .map(|c| {
+                let below = coords.partition_point(|&coord| coord < c);
+                let (c, k, below_sum) = (c as i128, below as i128, prefix[below]);
+                ((c * k - below_sum) + (total - below_sum - c * (n - k))) as u128
+            })
+            .collect();
+        Some(totals)
+    }
+}
+
+impl Metric for Chebyshev {
+    fn distance(&self, a: Point, b: Point) -> u128 {
+        max(abs_diff(a.x, b.x), abs_diff(a.y, b.y))
+    }
+}
+
+impl Metric for SquaredEuclidean {
+    fn distance(&self, a: Point, b: Point) -> u128 {
+        abs_diff(a.x, b.x).pow(2) + abs_diff(a.y, b.y).pow(2)
+    }
+
+    // A point on the boundary of the convex hull owns the locations far enough along the
+    // outward normal of its edge, while the area of a point inside is bounded by the
+    // bisectors with the points around it. Points sharing a location own nothing.
+    fn infinite_areas(&self, points: &[Point], owners: &Grid<Owner>) -> Option<Vec<bool>> {
+        Some(on_hull_boundary(points))
+    }
+
+    // The sum of `(c - coord)^2` expands to `n*c^2 - 2*c*sum(coord) + sum(coord^2)`
+    fn axis_totals(&self, coords: &[i64], from: i64, to: i64) -> Option<Vec<u128>> {
+        let n = coords.len() as i128;
+        let sum = coords.iter().map(|&c| c as i128).sum::<i128>();
+        let sum_squares = coords.iter().map(|&c| (c as i128).pow(2)).sum::<i128>();
+        let totals = (from..=to)
+            .map(|c| (n * (c as i128).pow(2) - 2 * c as i128 * sum + sum_squares) as u128)
+            .collect();
+        Some(totals)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Owner {
+    // Index of the closest point
+    Point(usize),
+    // Closest to more than one point
+    Tied,
+}
+
+#[allow(dead_code)]
+pub fn closest(points: &[Point], metric: &impl Metric, location: Point) -> Owner {
+    let mut owner = Owner::Tied;
+    let mut min_distance = u128::MAX;
+    for (idx, &p) in points.iter().enumerate() {
+        let distance = metric.distance(p, location);
+        if distance < min_distance {
+            min_distance = distance;
+            owner = Owner::Point(idx);
+        } else if distance == min_distance {
+            owner = Owner::Tied;
+        }
+    }
+    owner
+}
+
+// Twice the signed area of the triangle, positive when `a`, `b`, `c` turn counterclockwise
+fn cross(a: Point, b: Point, c: Point) -> i128 {
+    (b.x - a.x) as i128 * (c.y - a.y) as i128 - (b.y - a.y) as i128 * (c.x - a.x) as i128
+}
+
+// Vertices of the convex hull in counterclockwise order, by Andrew's monotone chain.
+// Points in the middle of an edge aren't vertices.
+fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort_unstable_by_key(|p| (p.x, p.y));
+    sorted.dedup();
+    if sorted.len() <= 2 {
+        return sorted;
+    }
+
+    let mut hull: Vec<Point> = Vec::new();
+    for chain in [sorted.clone(), sorted.into_iter().rev().collect()].iter() {
+        let start = hull.len();
+        for &p in chain.iter() {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point starts the other chain
+        hull.pop();
+    }
+    hull
+}
+
+// Whether each point is on the boundary of the convex hull of the points, at a location
+// no other point shares
+fn on_hull_boundary(points: &[Point]) -> Vec<bool> {
+    let hull = convex_hull(points);
+    let mut counts = HashMap::new();
+    for &p in points {
+        *counts.entry(p).or_insert(0) += 1;
+    }
+    points
+        .iter()
+        .map(|&p| {
+            // Every point is inside the hull, so being on the line of an edge is being on the edge
+            let on_edge = hull.len() <= 2 || (0..hull.len()).any(|i| cross(hull[i], hull[(i + 1) % hull.len()], p) == 0);
+            on_edge && counts[&p] == 1
+        })
+        .collect()
+}
+
+// Whether each point owns a location on the boundary of the box
+fn touching_boundary(owners: &Grid<Owner>, point_count: usize) -> Vec<bool> {
+    let (width, height) = (owners.width, owners.height());
+    let mut touching = vec![false; point_count];
+    for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+        if let Owner::Point(idx) = owners[(x, y)] {
+            touching[idx] |= x == 0 || y == 0 || x == width - 1 || y == height - 1;
+        }
+    }
+    touching
+}
+
+fn bounding_box(points: &[Point]) -> ((i64, i64), (i64, i64)) {
+    let (min_x, max_x) = points.iter().map(|p| p.x).minmax().into_option().expect("Input is empty");
+    let (min_y, max_y) = points.iter().map(|p| p.y).minmax().into_option().expect("Input is empty");
+    ((min_x, min_y), (max_x, max_y))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Area {
+    // Number of locations owned
+    Finite(usize),
+    Infinite,
+    // The metric can't tell if the area is infinite, or it's finite but reaches past the box
+    Unknown,
+}
+
+// Closest point of every location in a box around the points, boundary included
+pub struct AreaMap {
+    #[allow(dead_code)]
+    min: (i64, i64),
+    owners: Grid<Owner>,
+    areas: Vec<Area>,
+}
+
+impl AreaMap {
+    // With Manhattan distance the box is the bounding box of the points. Moving away from it
+    // increases the distance to every point by the same amount, so any location outside has
+    // the same owner as the nearest one on the boundary. Areas touching the boundary are then
+    // exactly the infinite ones.
+    //
+    // Multi-source BFS: a location at distance `d` from its closest points is owned by the
+    // union of the owners of its neighbours at distance `d - 1`.
+    pub fn new(points: &[Point]) -> Self {
+        let ((min_x, min_y), (max_x, max_y)) = bounding_box(points);
+        let width = (max_x - min_x + 1) as usize;
+        let height = (max_y - min_y + 1) as usize;
+
+        let mut owners = Grid { vec: vec![Owner::Tied; width * height], width };
+        let mut distances = Grid { vec: vec![usize::MAX; width * height], width };
+        let mut frontier = Vec::with_capacity(points.len());
+        for (idx, p) in points.iter().enumerate() {
+            let pos = ((p.x - min_x) as usize, (p.y - min_y) as usize);
+            if distances[pos] == 0 {
+                owners[pos] = Owner::Tied;
+            } else {
+                distances[pos] = 0;
+                owners[pos] = Owner::Point(idx);
+                frontier.push(pos);
+            }
+        }
+
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for &pos in frontier.iter() {
+                let owner = owners[pos];
+                for neighbour in owners.neighbours4(pos) {
+                    if distances[neighbour] == usize::MAX {
+                        distances[neighbour] = distance + 1;
+                        owners[neighbour] = owner;
+                        next.push(neighbour);
+                    } else if distances[neighbour] == distance + 1 && owners[neighbour] != owner {
+                        owners[neighbour] = Owner::Tied;
+                    }
+                }
+            }
+            frontier = next;
+            distance += 1;
+        }
+
+        let infinite = Manhattan.infinite_areas(points, &owners);
+        Self::from_owners((min_x, min_y), owners, points.len(), infinite)
     }
 
-    fn closest(points: &[Point], x: usize, y: usize) -> Option<Point> {
-        let mut min_d = points[0].dist(x, y);
-        let mut min_p = Some(points[0]);
+    // Finds the closest point of each location in the bounding box grown by the metric's margin.
+    // Areas are unknown when the metric can't tell which are infinite, or when a finite one
+    // reaches the edge of the grown box.
+    #[allow(dead_code)]
+    pub fn with_metric(points: &[Point], metric: &impl Metric) -> Self {
+        let ((min_x, min_y), (max_x, max_y)) = bounding_box(points);
+        let margin = metric.margin((max_x - min_x) as u64, (max_y - min_y) as u64) as i64;
+        let (min_x, min_y) = (min_x - margin, min_y - margin);
+        let width = (max_x + margin - min_x + 1) as usize;
+        let height = (max_y + margin - min_y + 1) as usize;
+
+        let vec = (0..width * height)
+            .into_par_iter()
+            .map(|idx| {
+                let location = Point { x: min_x + (idx % width) as i64, y: min_y + (idx / width) as i64 };
+                closest(points, metric, location)
+            })
+            .collect();
+        let owners = Grid { vec, width };
+        let infinite = metric.infinite_areas(points, &owners);
+        Self::from_owners((min_x, min_y), owners, points.len(), infinite)
+    }
 
-        for p in points.iter().skip(1).copied() {
-            let d = p.dist(x, y);
-            if d == min_d {
-                min_p = None;
-            } else if d < min_d {
-                min_d = d;
-                min_p = Some(p);
+    fn from_owners(min: (i64, i64), owners: Grid<Owner>, point_count: usize, infinite: Option<Vec<bool>>) -> Self {
+        let touching = touching_boundary(&owners, point_count);
+        let mut counts = vec![0; point_count];
+        for owner in owners.vec.iter() {
+            if let Owner::Point(idx) = *owner {
+                counts[idx] += 1;
+            }
+        }
+        let areas = (0..point_count)
+            .map(|idx| match infinite.as_ref().map(|infinite| infinite[idx]) {
+                Some(true) => Area::Infinite,
+                Some(false) if !touching[idx] => Area::Finite(counts[idx]),
+                _ => Area::Unknown,
+            })
+            .collect();
+        Self { min, owners, areas }
+    }
+
+    // Locations outside the box get the owner of the nearest location on its boundary,
+    // which is only exact with Manhattan distance
+    #[allow(dead_code)]
+    pub fn owner(&self, x: i64, y: i64) -> Owner {
+        let clamp = |v: i64, min: i64, len: usize| (v.max(min).min(min + len as i64 - 1) - min) as usize;
+        self.owners[(clamp(x, self.min.0, self.owners.width), clamp(y, self.min.1, self.owners.height()))]
+    }
+
+    // Area of the point with the given index
+    #[allow(dead_code)]
+    pub fn area(&self, idx: usize) -> Area {
+        self.areas[idx]
+    }
+
+    // Largest of the areas known to be finite
+    pub fn largest_finite_area(&self) -> Option<usize> {
+        self.areas
+            .iter()
+            .filter_map(|&area| if let Area::Finite(size) = area { Some(size) } else { None })
+            .max()
+    }
+}
+
+// Each point gets a letter and a color, areas not known to be finite are dimmed and ties are dots
+impl Render for AreaMap {
+    fn size(&self) -> (usize, usize) { (self.owners.width, self.owners.height()) }
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        const PALETTE: [Rgb; 5] = [Rgb::RED, Rgb::GREEN, Rgb::BLUE, Rgb::YELLOW, Rgb::BROWN];
+        const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+        match self.owners[(x, y)] {
+            Owner::Tied => Pixel::new('.', Rgb::GRAY),
+            Owner::Point(idx) => {
+                let color = PALETTE[idx % PALETTE.len()].mix(Rgb::WHITE, (idx / PALETTE.len() * 40 % 160) as u8);
+                let color = match self.areas[idx] {
+                    Area::Finite(_) => color,
+                    _ => color.mix(Rgb::BLACK, 160),
+                };
+                Pixel::new(LETTERS[idx % LETTERS.len()] as char, color)
             }
         }
-        min_p
     }
 }
 
@@ -39,34 +369,203 @@ pub fn input_generator(input: &str) -> Input {
 }
 
 pub fn part1(points: &Input) -> usize {
-    let width = points.iter().max_by_key(|p| p.x).expect("Input is empty").x;
-    let height = points.iter().max_by_key(|p| p.y).expect("Input is empty").y;
+    AreaMap::new(points).largest_finite_area().expect("There's no point with finite area")
+}
 
-    let mut infinite_points = HashSet::with_capacity(points.len() / 4);
-    let mut point_counts = HashMap::with_capacity(width * height);
-    for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
-        if let Some(point) = Point::closest(points, x, y) {
-            if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
-                infinite_points.insert(point);
-            }
-            *point_counts.entry(point).or_insert(0) += 1;
-        }
+// Number of locations whose total distance to the points is less than `threshold`. Each step
+// away from the bounding box adds at least the distance of one step along an axis to each
+// point's, which bounds how far past it the region can extend. When the metric is the sum of
+// a term for each axis, those are computed separately and the count takes O(W + H + N log N).
+pub fn safe_region_size(points: &[Point], threshold: u64, metric: &impl Metric) -> usize {
+    if points.is_empty() {
+        return 0;
+    }
+    let threshold = threshold as u128;
+    let n = points.len() as u128;
+    let (mut lo, mut hi) = (0, 1);
+    while n * metric.axis_distance(hi) < threshold {
+        lo = hi;
+        hi *= 2;
     }
+    // The smallest margin `m` with `n * axis_distance(m + 1) >= threshold`, beyond which
+    // every total is too large
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if n * metric.axis_distance(mid + 1) >= threshold { hi = mid } else { lo = mid + 1 }
+    }
+    let margin = lo as i64;
+
+    let ((min_x, min_y), (max_x, max_y)) = bounding_box(points);
+    let (xs, ys) = (points.iter().map(|p| p.x).collect::<Vec<_>>(), points.iter().map(|p| p.y).collect::<Vec<_>>());
+    let axis_totals = metric
+        .axis_totals(&xs, min_x - margin, max_x + margin)
+        .zip(metric.axis_totals(&ys, min_y - margin, max_y + margin));
 
-    point_counts
-        .into_iter()
-        .filter(|(point, _)| !infinite_points.contains(point))
-        .map(|(_, count)| count)
-        .max()
-        .expect("There's no point with finite area")
+    match axis_totals {
+        Some((x_totals, mut y_totals)) => {
+            y_totals.sort_unstable();
+            x_totals
+                .iter()
+                .filter(|&&x_total| x_total < threshold)
+                .map(|&x_total| y_totals.partition_point(|&y_total| y_total < threshold - x_total))
+                .sum()
+        }
+        None => (min_y - margin..=max_y + margin)
+            .into_par_iter()
+            .map(|y| {
+                (min_x - margin..=max_x + margin)
+                    .filter(|&x| {
+                        let total = points.iter().map(|&p| metric.distance(p, Point { x, y })).sum::<u128>();
+                        total < threshold
+                    })
+                    .count()
+            })
+            .sum(),
+    }
 }
 
 pub fn part2(points: &Input) -> usize {
-    let width = points.iter().max_by_key(|p| p.x).expect("Input is empty").x;
-    let height = points.iter().max_by_key(|p| p.y).expect("Input is empty").y;
+    safe_region_size(points, 10000, &Manhattan)
+}
+
+pub fn visualize(points: &Input, frames: &mut Frames) -> std::io::Result<()> {
+    frames.push(&AreaMap::new(points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
 
-    (0..width)
-        .flat_map(|x| (0..height).map(move |y| (x, y)))
-        .filter(|&(x, y)| points.iter().map(|p| p.dist(x, y)).sum::<usize>() < 10000)
-        .count()
+    fn example() -> Input {
+        input_generator("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9")
+    }
+
+    // Hides the metric's per-axis totals, so the region is counted by brute force
+    struct WithoutAxisTotals<M>(M);
+
+    impl<M: Metric> Metric for WithoutAxisTotals<M> {
+        fn distance(&self, a: Point, b: Point) -> u128 { self.0.distance(a, b) }
+        fn axis_distance(&self, d: u64) -> u128 { self.0.axis_distance(d) }
+        fn margin(&self, width: u64, height: u64) -> u64 { self.0.margin(width, height) }
+    }
+
+    // Counts every location within `reach` of the bounding box
+    fn brute_safe_region(points: &[Point], threshold: u64, metric: &impl Metric, reach: i64) -> usize {
+        let ((min_x, min_y), (max_x, max_y)) = bounding_box(points);
+        (min_y - reach..=max_y + reach)
+            .cartesian_product(min_x - reach..=max_x + reach)
+            .filter(|&(y, x)| points.iter().map(|&p| metric.distance(p, Point { x, y })).sum::<u128>() < threshold as u128)
+            .count()
+    }
+
+    fn random_points(rng: &mut Lcg, count: usize) -> Vec<Point> {
+        (0..count).map(|_| Point { x: rng.range(-20, 21), y: rng.range(-20, 21) }).collect()
+    }
+
+    #[test]
+    fn example_answers() {
+        let points = example();
+        assert_eq!(part1(&points), 17);
+        assert_eq!(safe_region_size(&points, 32, &Manhattan), 16);
+    }
+
+    #[test]
+    fn flood_fill_matches_manhattan_brute_force() {
+        let points = example();
+        let (flooded, brute) = (AreaMap::new(&points), AreaMap::with_metric(&points, &Manhattan));
+        assert_eq!(flooded.largest_finite_area(), Some(17));
+        assert_eq!(brute.largest_finite_area(), Some(17));
+        for idx in 0..points.len() {
+            assert_eq!(flooded.area(idx), brute.area(idx));
+        }
+        // D and E are the only finite areas
+        assert_eq!((0..points.len()).map(|idx| flooded.area(idx)).collect::<Vec<_>>(), [Area::Infinite, Area::Infinite, Area::Infinite, Area::Finite(9), Area::Finite(17), Area::Infinite]);
+        for (x, y) in (-5..15).cartesian_product(-5..15) {
+            assert_eq!(flooded.owner(x, y), brute.owner(x, y));
+            assert_eq!(flooded.owner(x, y), closest(&points, &Manhattan, Point { x, y }), "{}, {}", x, y);
+        }
+    }
+
+    fn areas(map: &AreaMap, count: usize) -> Vec<Area> {
+        (0..count).map(|idx| map.area(idx)).collect()
+    }
+
+    #[test]
+    fn other_metrics() {
+        let points = example();
+        assert_eq!(Chebyshev.distance(points[0], points[5]), 8);
+        assert_eq!(SquaredEuclidean.distance(points[0], points[5]), 7 * 7 + 8 * 8);
+        // Chebyshev can't tell which areas are infinite
+        assert_eq!(areas(&AreaMap::with_metric(&points, &Chebyshev), 6), [Area::Unknown; 6]);
+        assert_eq!(AreaMap::with_metric(&points, &Chebyshev).largest_finite_area(), None);
+        assert_eq!(safe_region_size(&points, 32, &Chebyshev), brute_safe_region(&points, 32, &Chebyshev, 10));
+    }
+
+    #[test]
+    fn squared_euclidean_areas() {
+        // Only D and E are inside the hull
+        let map = AreaMap::with_metric(&example(), &SquaredEuclidean);
+        assert_eq!(areas(&map, 6), [Area::Infinite, Area::Infinite, Area::Infinite, Area::Finite(10), Area::Finite(16), Area::Infinite]);
+        assert_eq!(map.largest_finite_area(), Some(16));
+
+        // The center owns the locations with 2 < x + y < 6 and |x - y| < 2
+        let square = input_generator("0, 0\n4, 0\n0, 4\n4, 4\n2, 2");
+        let map = AreaMap::with_metric(&square, &SquaredEuclidean);
+        assert_eq!(areas(&map, 5), [Area::Infinite, Area::Infinite, Area::Infinite, Area::Infinite, Area::Finite(5)]);
+
+        // Points sharing a location own nothing
+        let doubled = input_generator("0, 0\n4, 0\n0, 4\n4, 4\n2, 2\n2, 2");
+        let map = AreaMap::with_metric(&doubled, &SquaredEuclidean);
+        assert_eq!(areas(&map, 6)[4..], [Area::Finite(0), Area::Finite(0)]);
+
+        // 4, 0 is in the middle of an edge of the hull, and owns the locations below it with
+        // 2 <= x <= 6. 4, 2 owns those with 1 < y < 4, 2x + y > 5 and 2x - y < 11.
+        let edge = input_generator("0, 0\n4, 0\n8, 0\n4, 6\n4, 2");
+        let map = AreaMap::with_metric(&edge, &SquaredEuclidean);
+        assert_eq!(areas(&map, 5), [Area::Infinite, Area::Infinite, Area::Infinite, Area::Infinite, Area::Finite(10)]);
+
+        // Collinear points all have infinite areas
+        let line = input_generator("0, 0\n1, 1\n3, 3");
+        assert_eq!(areas(&AreaMap::with_metric(&line, &SquaredEuclidean), 3), [Area::Infinite; 3]);
+    }
+
+    #[test]
+    fn finite_areas_past_the_box_are_unknown() {
+        // 10, 1 owns the locations with y < 2, 20x + 2y > 101 and 20x - 2y < 299, which
+        // reach down to y = -49, past the margin of 20
+        let points = input_generator("0, 0\n20, 0\n10, 3\n10, 1");
+        let map = AreaMap::with_metric(&points, &SquaredEuclidean);
+        assert_eq!(areas(&map, 4), [Area::Infinite, Area::Infinite, Area::Infinite, Area::Unknown]);
+        assert_eq!(map.largest_finite_area(), None);
+    }
+
+    #[test]
+    fn axis_totals_match_brute_force() {
+        let mut rng = Lcg::new(3);
+        for count in 1..12 {
+            let points = random_points(&mut rng, count);
+            for &threshold in [1, 10, 100, 400].iter() {
+                assert_eq!(
+                    safe_region_size(&points, threshold, &Manhattan),
+                    safe_region_size(&points, threshold, &WithoutAxisTotals(Manhattan)),
+                );
+                assert_eq!(
+                    safe_region_size(&points, threshold * 20, &SquaredEuclidean),
+                    safe_region_size(&points, threshold * 20, &WithoutAxisTotals(SquaredEuclidean)),
+                );
+                assert_eq!(
+                    safe_region_size(&points, threshold, &Manhattan),
+                    brute_safe_region(&points, threshold, &Manhattan, threshold as i64 / count as i64 + 1),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn coordinates_past_i16() {
+        let points = input_generator("-40000, 40000\n40000, -40000\n0, 0");
+        assert_eq!(Manhattan.distance(points[0], points[1]), 160_000);
+        assert_eq!(safe_region_size(&points, 160_001, &Manhattan), 1);
+    }
 }
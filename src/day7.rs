@@ -1,74 +1,521 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
-type Input = BTreeMap<char, Vec<char>>;
+use std::fmt;
+type Input = BTreeMap<String, Vec<String>>;
+
+#[derive(FromStr, Debug)]
+#[display("Step {before} must be finished before step {after} can begin.")]
+struct Dependency {
+    before: String,
+    after: String,
+}
 
 pub fn input_generator(input: &str) -> Input {
     let mut steps = BTreeMap::new();
 
     for line in input.lines() {
-        let c1 = line.chars().nth(5).expect("Invalid input");
-        let c2 = line.chars().nth(36).expect("Invalid input");
+        let Dependency { before, after } = line.parse().expect("Invalid input");
+        steps.entry(before.clone()).or_insert_with(Vec::new);
+        steps.entry(after).or_insert_with(Vec::new).push(before);
+    }
+
+    steps
+}
+
+// Steps that depend on each other, each one has to be done before the next and the last
+// before the first
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CycleError {
+    pub cycle: Vec<String>,
+}
 
-        steps.entry(c1).or_insert(vec![]);
-        steps.entry(c2).or_insert(vec![]).push(c1);
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut steps = self.cycle.iter().chain(self.cycle.first());
+        write!(f, "Cyclic dependencies: {}", steps.join(" -> "))
     }
+}
+
+// Depth first search along the dependencies, a step found again while it's still on
+// the path closes a cycle
+pub fn find_cycle(steps: &Input) -> Option<CycleError> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum State { Visiting, Done }
 
+    fn visit<'a>(
+        step: &'a str,
+        steps: &'a Input,
+        states: &mut HashMap<&'a str, State>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        match states.get(step) {
+            Some(State::Done) => return None,
+            Some(State::Visiting) => {
+                let start = path.iter().position(|&s| s == step).unwrap();
+                // The path goes from each step to one of its dependencies, so it's reversed
+                return Some(path[start..].iter().rev().map(|s| s.to_string()).collect());
+            }
+            None => {}
+        }
+        states.insert(step, State::Visiting);
+        path.push(step);
+        for dep in steps.get(step).into_iter().flatten() {
+            if let Some(cycle) = visit(dep, steps, states, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        states.insert(step, State::Done);
+        None
+    }
+
+    let mut states = HashMap::new();
     steps
+        .keys()
+        .find_map(|step| visit(step, steps, &mut states, &mut Vec::new()))
+        .map(|cycle| CycleError { cycle })
 }
 
-pub fn part1(steps: &Input) -> String {
-    let mut steps = steps.clone();
-    let mut output = String::new();
-
-    while steps.len() != 0 {
-        let first = *steps.iter().find(|(_, v)| v.len() == 0).expect("Cyclic dependencies!").0;
-        output.push(first);
-        steps.remove(&first);
-        for deps in steps.values_mut() {
-            if let Some(p) = deps.iter().position(|&r| r == first) {
-                deps.swap_remove(p);
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Task {
+    pub step: String,
+    pub worker: usize,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Schedule {
+    pub workers: usize,
+    // Ordered by start time, then by worker
+    pub tasks: Vec<Task>,
+    pub total_time: u32,
+}
+
+impl Schedule {
+    pub fn order(&self) -> Vec<&str> {
+        self.tasks.iter().map(|task| task.step.as_str()).collect()
+    }
+
+    // Periods of time, as `(worker, start, end)`, in which a worker has nothing to do
+    #[allow(dead_code)]
+    pub fn idle_periods(&self) -> Vec<(usize, u32, u32)> {
+        let mut periods = Vec::new();
+        for worker in 0..self.workers {
+            let mut free_since = 0;
+            for task in self.tasks.iter().filter(|task| task.worker == worker) {
+                if task.start > free_since {
+                    periods.push((worker, free_since, task.start));
+                }
+                free_since = task.end;
+            }
+            if self.total_time > free_since {
+                periods.push((worker, free_since, self.total_time));
+            }
+        }
+        periods
+    }
+}
+
+// Gantt chart with a row per worker and a column per second, idle seconds are dots.
+// Steps with longer names repeat them over the seconds they take.
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label_width = format!("Worker {}", self.workers).len();
+        let ruler = (0..self.total_time)
+            .map(|t| if t % 10 == 0 { '|' } else { ' ' })
+            .collect::<String>();
+        writeln!(f, "{:width$}  {}", "", ruler.trim_end(), width = label_width)?;
+
+        for worker in 0..self.workers {
+            let mut row = vec!['.'; self.total_time as usize];
+            for task in self.tasks.iter().filter(|task| task.worker == worker) {
+                let span = &mut row[task.start as usize..task.end as usize];
+                span.iter_mut().zip(task.step.chars().cycle()).for_each(|(c, name)| *c = name);
             }
+            let label = format!("Worker {}", worker + 1);
+            writeln!(f, "{:width$}  {}", label, row.into_iter().collect::<String>(), width = label_width)?;
         }
+        Ok(())
     }
+}
 
-    output
+fn dependents(steps: &Input) -> HashMap<&str, Vec<&str>> {
+    let mut dependents = HashMap::<&str, Vec<&str>>::new();
+    for (step, deps) in steps.iter() {
+        for dep in deps.iter() {
+            dependents.entry(dep.as_str()).or_default().push(step.as_str());
+        }
+    }
+    dependents
 }
 
-pub fn part2(steps: &Input) -> u32 {
-    let mut steps = steps.clone();
-    let mut workers: [Option<(char, u32)>; 5] = [None ; 5];
+// Whenever a worker is free it picks the available step that comes first alphabetically,
+// lower numbered workers pick first.
+pub fn schedule(steps: &Input, workers: usize, duration: impl Fn(&str) -> u32) -> Result<Schedule, CycleError> {
+    assert!(workers > 0, "There must be at least one worker");
+    if let Some(cycle) = find_cycle(steps) {
+        return Err(cycle);
+    }
+
+    let mut missing = steps.iter().map(|(step, deps)| (step.as_str(), deps.len())).collect::<BTreeMap<_, _>>();
+    let dependents = dependents(steps);
+
+    let mut running: Vec<Option<(&str, u32)>> = vec![None; workers];
+    let mut tasks = Vec::with_capacity(steps.len());
     let mut time = 0;
 
     loop {
-        for option in workers.iter_mut() {
-            if let Some((c, time_left)) = option {
-                *time_left -= 1;
-                if *time_left == 0 {
-                    for (_, deps) in steps.iter_mut() {
-                        if let Some(p) = deps.iter().position(|&r| r == *c) {
-                            deps.swap_remove(p);
-                        }
+        for slot in running.iter_mut() {
+            if let Some((step, end)) = *slot {
+                if end <= time {
+                    for dependent in dependents.get(step).into_iter().flatten() {
+                        *missing.get_mut(dependent).unwrap() -= 1;
                     }
-                    *option = None;
+                    *slot = None;
                 }
             }
         }
 
-        for option in workers.iter_mut() {
-            if *option == None {
-                if let Some((&c, _)) = steps.iter().find(|(_, v)| v.len() == 0) {
-                    *option = Some((c, 60 + (c as u32 - 64)));
-                    steps.remove(&c);
-                }
+        for (worker, slot) in running.iter_mut().enumerate().filter(|(_, slot)| slot.is_none()) {
+            if let Some(step) = missing.iter().find(|&(_, &count)| count == 0).map(|(&step, _)| step) {
+                missing.remove(&step);
+                let end = time + duration(step);
+                tasks.push(Task { step: step.to_string(), worker, start: time, end });
+                *slot = Some((step, end));
             }
         }
 
-        if workers.iter().all(Option::is_none) {
-            break;
+        match running.iter().flatten().map(|&(_, end)| end).min() {
+            Some(end) => time = end,
+            None => break,
+        }
+    }
+
+    Ok(Schedule { workers, tasks, total_time: time })
+}
+
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CriticalPath {
+    pub steps: Vec<String>,
+    pub length: u32,
+}
+
+// The chain of dependencies taking the longest time, which bounds the total time no matter
+// how many workers there are. Ties go to the dependency that comes first.
+#[allow(dead_code)]
+pub fn critical_path(steps: &Input, duration: impl Fn(&str) -> u32) -> Result<CriticalPath, CycleError> {
+    if let Some(cycle) = find_cycle(steps) {
+        return Err(cycle);
+    }
+
+    // Time at which each step can end at the earliest, and the dependency holding it back
+    fn finish<'a>(
+        step: &'a str,
+        steps: &'a Input,
+        duration: &impl Fn(&str) -> u32,
+        memo: &mut HashMap<&'a str, (u32, Option<&'a str>)>,
+    ) -> u32 {
+        if let Some(&(end, _)) = memo.get(step) {
+            return end;
+        }
+        let mut latest = (0, None);
+        for dep in steps.get(step).into_iter().flatten() {
+            let end = finish(dep, steps, duration, memo);
+            if latest.1.is_none() || end > latest.0 {
+                latest = (end, Some(dep.as_str()));
+            }
+        }
+        let end = latest.0 + duration(step);
+        memo.insert(step, (end, latest.1));
+        end
+    }
+
+    let mut memo = HashMap::new();
+    let last = steps.keys().map(|step| (finish(step, steps, &duration, &mut memo), step.as_str())).fold(
+        None,
+        |best: Option<(u32, &str)>, (end, step)| match best {
+            Some((best_end, _)) if best_end >= end => best,
+            _ => Some((end, step)),
+        },
+    );
+
+    let mut path = Vec::new();
+    let mut current = last.map(|(_, step)| step);
+    while let Some(step) = current {
+        path.push(step.to_string());
+        current = memo[step].1;
+    }
+    path.reverse();
+    Ok(CriticalPath { steps: path, length: last.map_or(0, |(end, _)| end) })
+}
+
+// Graphviz graph with an edge from each step to the ones depending on it
+#[allow(dead_code)]
+pub fn to_dot(steps: &Input) -> String {
+    let mut dot = String::from("digraph steps {\n");
+    for (step, deps) in steps.iter() {
+        if deps.is_empty() {
+            dot += &format!("    {:?};\n", step);
+        }
+        for dep in deps.iter() {
+            dot += &format!("    {:?} -> {:?};\n", dep, step);
+        }
+    }
+    dot.push('}');
+    dot
+}
+
+// Steps numbered alphabetically, with their dependencies as bit sets of at most 128 steps
+#[allow(dead_code)]
+struct Orders<'a> {
+    names: Vec<&'a str>,
+    deps: Vec<u128>,
+    counts: HashMap<u128, Option<u128>>,
+}
+
+#[allow(dead_code)]
+impl<'a> Orders<'a> {
+    fn new(steps: &'a Input) -> Option<Self> {
+        if steps.len() > 128 {
+            return None;
         }
+        let names = steps.keys().map(|step| step.as_str()).collect::<Vec<_>>();
+        let index = |name: &str| names.binary_search(&name).unwrap();
+        let deps = steps.values().map(|deps| deps.iter().fold(0, |mask, dep| mask | 1 << index(dep))).collect();
+        Some(Self { names, deps, counts: HashMap::new() })
+    }
+
+    fn all(&self) -> u128 {
+        if self.names.len() == 128 { u128::MAX } else { (1 << self.names.len()) - 1 }
+    }
+
+    // Steps not done yet whose dependencies all are, alphabetically
+    fn available(&self, done: u128) -> impl Iterator<Item = usize> + '_ {
+        (0..self.names.len()).filter(move |&idx| done & 1 << idx == 0 && self.deps[idx] & !done == 0)
+    }
+
+    // Number of ways to complete the steps after the ones in `done`, None if it overflows.
+    // Only sets of steps closed under dependencies are reached: few for the puzzle's graphs,
+    // but up to 2^N when the steps are mostly independent.
+    fn count(&mut self, done: u128) -> Option<u128> {
+        if done == self.all() {
+            return Some(1);
+        }
+        if let Some(&count) = self.counts.get(&done) {
+            return count;
+        }
+        let available = self.available(done).collect::<Vec<_>>();
+        let count = available
+            .into_iter()
+            .try_fold(0u128, |count, idx| count.checked_add(self.count(done | 1 << idx)?));
+        self.counts.insert(done, count);
+        count
+    }
+}
+
+// Number of orders in which the steps can be done, None if it doesn't fit in a u128 or
+// there are more than 128 steps
+#[allow(dead_code)]
+pub fn count_orders(steps: &Input) -> Option<u128> {
+    Orders::new(steps)?.count(0)
+}
+
+// The `k`-th valid order in lexicographic order of the step names, starting from 0. None
+// past the last order or with more than 128 steps.
+#[allow(dead_code)]
+pub fn kth_order(steps: &Input, mut k: u128) -> Option<Vec<String>> {
+    let mut orders = Orders::new(steps)?;
+    let mut done = 0;
+    let mut order = Vec::with_capacity(steps.len());
+
+    while done != orders.all() {
+        let available = orders.available(done).collect::<Vec<_>>();
+        let mut chosen = None;
+        for idx in available {
+            match orders.count(done | 1 << idx) {
+                Some(count) if k >= count => k -= count,
+                _ => {
+                    chosen = Some(idx);
+                    break;
+                }
+            }
+        }
+        let idx = chosen?;
+        order.push(orders.names[idx].to_string());
+        done |= 1 << idx;
+    }
+    Some(order)
+}
+
+pub fn part1(steps: &Input) -> String {
+    schedule(steps, 1, |_| 1)
+        .unwrap_or_else(|cycle| panic!("{}", cycle))
+        .order()
+        .concat()
+}
+
+// Steps take 60 seconds plus the position of their letter in the alphabet
+fn step_duration(step: &str) -> u32 {
+    match step.as_bytes() {
+        &[c @ b'A'..=b'Z'] => 60 + (c - b'A' + 1) as u32,
+        _ => panic!("Step {:?} is not a single capital letter", step),
+    }
+}
+
+pub fn part2(steps: &Input) -> u32 {
+    schedule(steps, 5, step_duration)
+        .unwrap_or_else(|cycle| panic!("{}", cycle))
+        .total_time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        time += 1;
+    const EXAMPLE: &str = "\
+Step C must be finished before step A can begin.
+Step C must be finished before step F can begin.
+Step A must be finished before step B can begin.
+Step A must be finished before step D can begin.
+Step B must be finished before step E can begin.
+Step D must be finished before step E can begin.
+Step F must be finished before step E can begin.";
+
+    fn letter_duration(step: &str) -> u32 {
+        (step.as_bytes()[0] - b'A' + 1) as u32
     }
 
-    time
+    fn task(step: &str, worker: usize, start: u32, end: u32) -> Task {
+        Task { step: step.to_string(), worker, start, end }
+    }
+
+    #[test]
+    fn example_schedule() {
+        let steps = input_generator(EXAMPLE);
+        assert_eq!(part1(&steps), "CABDFE");
+
+        let schedule = schedule(&steps, 2, letter_duration).unwrap();
+        assert_eq!(schedule.total_time, 15);
+        assert_eq!(schedule.order(), ["C", "A", "F", "B", "D", "E"]);
+        assert_eq!(
+            schedule.tasks,
+            [
+                task("C", 0, 0, 3),
+                task("A", 0, 3, 4),
+                task("F", 1, 3, 9),
+                task("B", 0, 4, 6),
+                task("D", 0, 6, 10),
+                task("E", 0, 10, 15),
+            ]
+        );
+        assert_eq!(schedule.idle_periods(), [(1, 0, 3), (1, 9, 15)]);
+    }
+
+    #[test]
+    fn gantt_chart() {
+        let schedule = schedule(&input_generator(EXAMPLE), 2, letter_duration).unwrap();
+        assert_eq!(
+            schedule.to_string().lines().collect::<Vec<_>>(),
+            ["          |         |", "Worker 1  CCCABBDDDDEEEEE", "Worker 2  ...FFFFFF......"]
+        );
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let mut steps = input_generator(EXAMPLE);
+        // E -> B closes B -> E
+        steps.get_mut("B").unwrap().push("E".to_string());
+        let error = schedule(&steps, 2, letter_duration).unwrap_err();
+        assert_eq!(error.cycle, ["E", "B"]);
+        assert_eq!(error.to_string(), "Cyclic dependencies: E -> B -> E");
+
+        let steps = input_generator("\
+Step A must be finished before step B can begin.
+Step B must be finished before step C can begin.
+Step C must be finished before step A can begin.
+Step X must be finished before step Y can begin.");
+        assert_eq!(find_cycle(&steps).unwrap().to_string(), "Cyclic dependencies: B -> C -> A -> B");
+        assert!(find_cycle(&input_generator(EXAMPLE)).is_none());
+    }
+
+    #[test]
+    fn critical_path_bounds_the_time() {
+        let steps = input_generator(EXAMPLE);
+        let path = critical_path(&steps, letter_duration).unwrap();
+        assert_eq!(path, CriticalPath { steps: vec!["C".to_string(), "F".to_string(), "E".to_string()], length: 14 });
+        // With enough workers only the dependencies matter
+        assert_eq!(schedule(&steps, 6, letter_duration).unwrap().total_time, 14);
+        assert_eq!(schedule(&steps, 2, letter_duration).unwrap().total_time, 15);
+    }
+
+    #[test]
+    fn counts_and_enumerates_orders() {
+        let steps = input_generator(EXAMPLE);
+        // C first and E last, F anywhere among A, then B and D in either order
+        assert_eq!(count_orders(&steps), Some(8));
+        assert_eq!(kth_order(&steps, 0).unwrap().concat(), part1(&steps));
+        assert_eq!(kth_order(&steps, 1).unwrap().concat(), "CABFDE");
+        assert_eq!(kth_order(&steps, 7).unwrap().concat(), "CFADBE");
+        assert_eq!(kth_order(&steps, 8), None);
+    }
+
+    #[test]
+    fn orders_of_more_than_128_steps() {
+        let chain = |len: usize| {
+            let lines = (1..len).map(|i| format!("Step S{:03} must be finished before step S{:03} can begin.", i - 1, i));
+            input_generator(&lines.collect::<Vec<_>>().join("\n"))
+        };
+        assert_eq!(count_orders(&chain(128)), Some(1));
+        assert_eq!(kth_order(&chain(128), 0).unwrap().len(), 128);
+        assert_eq!(count_orders(&chain(129)), None);
+        assert_eq!(kth_order(&chain(129), 0), None);
+    }
+
+    #[test]
+    fn dot_export() {
+        let dot = to_dot(&input_generator(EXAMPLE));
+        let mut lines = dot.lines().collect::<Vec<_>>();
+        assert_eq!((lines.remove(0), lines.pop()), ("digraph steps {", Some("}")));
+        lines.sort_unstable();
+        assert_eq!(
+            lines,
+            [
+                r#"    "A" -> "B";"#,
+                r#"    "A" -> "D";"#,
+                r#"    "B" -> "E";"#,
+                r#"    "C" -> "A";"#,
+                r#"    "C" -> "F";"#,
+                r#"    "C";"#,
+                r#"    "D" -> "E";"#,
+                r#"    "F" -> "E";"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_character_names() {
+        let steps = input_generator("\
+Step fetch must be finished before step build can begin.
+Step configure must be finished before step build can begin.
+Step build must be finished before step test can begin.
+Step build must be finished before step package can begin.
+Step \"quoted\" must be finished before step test can begin.");
+        assert_eq!(steps.keys().collect::<Vec<_>>(), ["\"quoted\"", "build", "configure", "fetch", "package", "test"]);
+
+        let order = schedule(&steps, 1, |_| 1).unwrap().order().join(" ");
+        assert_eq!(order, "\"quoted\" configure fetch build package test");
+
+        let length = |step: &str| step.len() as u32;
+        let path = critical_path(&steps, length).unwrap();
+        assert_eq!(path.steps, ["configure", "build", "package"]);
+        assert_eq!(path.length, 9 + 5 + 7);
+
+        // 2 orders of fetch and configure, then the quoted step goes anywhere before test:
+        // 4 places when test comes before package, 5 when it comes last
+        assert_eq!(count_orders(&steps), Some(2 * (4 + 5)));
+        assert_eq!(kth_order(&steps, 0).unwrap().join(" "), order);
+        assert!(to_dot(&steps).contains(r#"    "\"quoted\"" -> "test";"#));
+    }
 }
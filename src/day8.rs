@@ -1,5 +1,6 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use std::fmt;
 type Input = Node;
 
 pub struct Node {
@@ -7,47 +8,414 @@ pub struct Node {
     metadata: Vec<u32>,
 }
 
-pub fn input_generator(input: &str) -> Input {
-    fn parse_node(iter: &mut impl Iterator<Item = u32>) -> Node {
-        let child_count = iter.next().expect("Invalid input");
-        let metadata_count = iter.next().expect("Invalid input");
+impl Node {
+    pub fn new(childs: Vec<Node>, metadata: Vec<u32>) -> Self {
+        Self { childs, metadata }
+    }
+
+    #[allow(dead_code)]
+    pub fn childs(&self) -> &[Node] { &self.childs }
 
-        let childs = (0..child_count)
-            .map(|_| parse_node(iter))
-            .collect();
-        let metadata = (0..metadata_count)
-            .map(|_| iter.next().expect("Invalid input"))
-            .collect();
+    #[allow(dead_code)]
+    pub fn metadata(&self) -> &[u32] { &self.metadata }
+}
+
+// The default drop would recurse once per level and overflow the stack on deep trees
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.childs);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.childs);
+        }
+    }
+}
 
-        Node { childs, metadata }
+// Shown in the input format, since a derived implementation would recurse
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Node({})", serialize(self))
     }
+}
 
-    parse_node(
-        &mut input
-            .split_whitespace()
-            .map(|p| p.parse().expect("Invalid input")),
-    )
+// Folds the tree bottom up without recursion, `f` gets each node with the results of its childs
+pub fn fold_tree<'a, T>(root: &'a Node, mut f: impl FnMut(&'a Node, Vec<T>) -> T) -> T {
+    let mut stack = vec![(root, Vec::with_capacity(root.childs.len()))];
+    loop {
+        let (node, results) = stack.last_mut().unwrap();
+        match node.childs.get(results.len()) {
+            Some(child) => stack.push((child, Vec::with_capacity(child.childs.len()))),
+            None => {
+                let (node, results) = stack.pop().unwrap();
+                let result = f(node, results);
+                match stack.last_mut() {
+                    Some((_, parent_results)) => parent_results.push(result),
+                    None => return result,
+                }
+            }
+        }
+    }
 }
 
-pub fn part1(input: &Input) -> u32 {
-    fn sum_metadata(node: &Node) -> u32 {
-        node.metadata.iter().sum::<u32>() + node.childs.iter().map(sum_metadata).sum::<u32>()
+// Positions are indexes in the list of numbers, starting from 0
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    InvalidNumber { position: usize, text: String },
+    MissingHeader { position: usize },
+    MissingMetadata { position: usize, node: usize, expected: u32 },
+    TrailingNumbers { position: usize, count: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber { position, text } => write!(f, "number {}: {:?} is not a number", position, text),
+            ParseError::MissingHeader { position } => write!(f, "number {}: the input ends inside a node header", position),
+            ParseError::MissingMetadata { position, node, expected } => write!(
+                f,
+                "number {}: the input ends before the {} metadata entries of the node starting at {}",
+                position, expected, node
+            ),
+            ParseError::TrailingNumbers { position, count } => {
+                write!(f, "number {}: {} numbers left after the root node", position, count)
+            }
+        }
+    }
+}
+
+struct Numbers<I> {
+    iter: I,
+    position: usize,
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Numbers<I> {
+    // `missing` builds the error for when the input ends at the current position
+    fn next(&mut self, missing: impl FnOnce(usize) -> ParseError) -> Result<u32, ParseError> {
+        let position = self.position;
+        let text = self.iter.next().ok_or_else(|| missing(position))?;
+        self.position += 1;
+        text.parse().map_err(|_| ParseError::InvalidNumber { position, text: text.to_string() })
+    }
+
+    fn header(&mut self) -> Result<Partial, ParseError> {
+        let start = self.position;
+        let childs_left = self.next(|position| ParseError::MissingHeader { position })?;
+        let metadata_count = self.next(|position| ParseError::MissingHeader { position })?;
+        // The counts aren't trusted enough to preallocate
+        Ok(Partial { start, childs_left, metadata_count, node: Node::new(Vec::new(), Vec::new()) })
+    }
+}
+
+// A node whose childs and metadata are still being read
+struct Partial {
+    start: usize,
+    childs_left: u32,
+    metadata_count: u32,
+    node: Node,
+}
+
+// Parses with an explicit stack, so the depth of the tree is only limited by memory
+pub fn parse(input: &str) -> Result<Node, ParseError> {
+    let mut numbers = Numbers { iter: input.split_whitespace(), position: 0 };
+
+    let mut stack = vec![numbers.header()?];
+    let root = loop {
+        let top = stack.last_mut().unwrap();
+        if top.childs_left > 0 {
+            top.childs_left -= 1;
+            let child = numbers.header()?;
+            stack.push(child);
+            continue;
+        }
+
+        let (node, expected) = (top.start, top.metadata_count);
+        for _ in 0..expected {
+            let entry = numbers.next(|position| ParseError::MissingMetadata { position, node, expected })?;
+            top.node.metadata.push(entry);
+        }
+        let done = stack.pop().unwrap().node;
+        match stack.last_mut() {
+            Some(parent) => parent.node.childs.push(done),
+            None => break done,
+        }
+    };
+
+    let count = numbers.iter.count();
+    if count > 0 {
+        return Err(ParseError::TrailingNumbers { position: numbers.position, count });
+    }
+    Ok(root)
+}
+
+// Writes the tree back in the same format it's parsed from, without recursion
+pub fn serialize(root: &Node) -> String {
+    enum Visit<'a> {
+        Header(&'a Node),
+        Metadata(&'a Node),
+    }
+
+    let mut numbers = Vec::new();
+    let mut stack = vec![Visit::Header(root)];
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Header(node) => {
+                numbers.push(node.childs.len() as u32);
+                numbers.push(node.metadata.len() as u32);
+                stack.push(Visit::Metadata(node));
+                stack.extend(node.childs.iter().rev().map(Visit::Header));
+            }
+            Visit::Metadata(node) => numbers.extend_from_slice(&node.metadata),
+        }
+    }
+    numbers.iter().join(" ")
+}
+
+// A metadata entry of a node with childs that doesn't refer to any of them
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InvalidReference {
+    pub node: usize,
+    // Index of the entry in the node's metadata
+    pub entry: usize,
+    pub reference: u32,
+    pub child_count: usize,
+}
+
+impl fmt::Display for InvalidReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "node #{}, metadata entry {}: refers to child {} but the node has {} childs",
+            self.node, self.entry, self.reference, self.child_count
+        )
+    }
+}
+
+// Flattened view of a tree for queries. Nodes are numbered in the order they appear in the
+// input, so the root is 0 and every node comes before its childs.
+pub struct TreeView<'a> {
+    nodes: Vec<&'a Node>,
+    parents: Vec<Option<usize>>,
+    childs: Vec<Vec<usize>>,
+    depths: Vec<usize>,
+    values: Vec<u32>,
+}
+
+impl<'a> TreeView<'a> {
+    pub fn new(root: &'a Node) -> Self {
+        let mut view = TreeView {
+            nodes: Vec::new(),
+            parents: Vec::new(),
+            childs: Vec::new(),
+            depths: Vec::new(),
+            values: Vec::new(),
+        };
+        let mut stack = vec![(root, None::<usize>, 0)];
+        while let Some((node, parent, depth)) = stack.pop() {
+            let id = view.nodes.len();
+            if let Some(parent) = parent {
+                view.childs[parent].push(id);
+            }
+            view.nodes.push(node);
+            view.parents.push(parent);
+            view.childs.push(Vec::with_capacity(node.childs.len()));
+            view.depths.push(depth);
+            stack.extend(node.childs.iter().rev().map(|child| (child, Some(id), depth + 1)));
+        }
+
+        // Childs come after their parent, so going backwards their values are already known
+        view.values = vec![0; view.nodes.len()];
+        for id in (0..view.nodes.len()).rev() {
+            let (node, childs) = (view.nodes[id], &view.childs[id]);
+            view.values[id] = if childs.is_empty() {
+                node.metadata.iter().sum()
+            } else {
+                node.metadata
+                    .iter()
+                    .filter_map(|&i| childs.get((i as usize).wrapping_sub(1)))
+                    .map(|&child| view.values[child])
+                    .sum()
+            };
+        }
+        view
+    }
+
+    #[allow(dead_code)]
+    pub fn node_count(&self) -> usize { self.nodes.len() }
+
+    // Number of levels of the tree, a lone root has depth 1
+    #[allow(dead_code)]
+    pub fn depth(&self) -> usize { self.depths.iter().max().map_or(0, |depth| depth + 1) }
+
+    #[allow(dead_code)]
+    pub fn node(&self, id: usize) -> &'a Node { self.nodes[id] }
+
+    #[allow(dead_code)]
+    pub fn node_depth(&self, id: usize) -> usize { self.depths[id] }
+
+    pub fn value(&self, id: usize) -> u32 { self.values[id] }
+
+    // Ids of the nodes from the root to `id`, both included
+    #[allow(dead_code)]
+    pub fn path(&self, id: usize) -> Vec<usize> {
+        let mut path = iter::successors(Some(id), |&id| self.parents[id]).collect::<Vec<_>>();
+        path.reverse();
+        path
+    }
+
+    #[allow(dead_code)]
+    pub fn invalid_references(&self) -> Vec<InvalidReference> {
+        let mut invalid = Vec::new();
+        for (id, node) in self.nodes.iter().enumerate().filter(|(_, node)| !node.childs.is_empty()) {
+            for (entry, &reference) in node.metadata.iter().enumerate() {
+                if reference == 0 || reference as usize > node.childs.len() {
+                    invalid.push(InvalidReference { node: id, entry, reference, child_count: node.childs.len() });
+                }
+            }
+        }
+        invalid
+    }
+
+    #[allow(dead_code)]
+    fn describe(&self, id: usize) -> String {
+        format!("#{} metadata [{}] value {}", id, self.nodes[id].metadata.iter().join(", "), self.values[id])
+    }
+
+    // One line per node, indented by depth
+    #[allow(dead_code)]
+    pub fn render_indented(&self) -> String {
+        let mut out = String::new();
+        for id in 0..self.nodes.len() {
+            out += &format!("{}{}\n", "  ".repeat(self.depths[id]), self.describe(id));
+        }
+        out
+    }
+
+    // Graphviz graph with the metadata and value of each node
+    #[allow(dead_code)]
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph tree {\n");
+        for id in 0..self.nodes.len() {
+            dot += &format!("    n{} [label={:?}];\n", id, self.describe(id));
+            for child in self.childs[id].iter() {
+                dot += &format!("    n{} -> n{};\n", id, child);
+            }
+        }
+        dot.push('}');
+        dot
     }
+}
+
+pub fn input_generator(input: &str) -> Input {
+    parse(input).unwrap_or_else(|error| panic!("Invalid input: {}", error))
+}
 
-    sum_metadata(input)
+pub fn part1(input: &Input) -> u32 {
+    fold_tree(input, |node, sums: Vec<u32>| node.metadata.iter().sum::<u32>() + sums.iter().sum::<u32>())
 }
 
 pub fn part2(input: &Input) -> u32 {
-    fn value(node: &Node) -> u32 {
-        if node.childs.len() == 0 {
-            node.metadata.iter().sum()
-        } else {
-            node.metadata
-                .iter()
-                .filter_map(|i| node.childs.get((*i - 1) as usize).map(value))
-                .sum()
+    TreeView::new(input).value(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    const EXAMPLE: &str = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+
+    // Random tree with up to `depth` levels
+    fn random_tree(rng: &mut Lcg, depth: usize) -> Node {
+        let child_count = if depth > 1 { rng.below(4) } else { 0 };
+        let metadata = (0..rng.below(4)).map(|_| rng.below(20)).collect::<Vec<_>>();
+        let childs = (0..child_count).map(|_| random_tree(rng, depth - 1)).collect();
+        Node::new(childs, metadata)
+    }
+
+    #[test]
+    fn example() {
+        let tree = input_generator(EXAMPLE);
+        assert_eq!((part1(&tree), part2(&tree)), (138, 66));
+        assert_eq!(serialize(&tree), EXAMPLE);
+    }
+
+    #[test]
+    fn round_trips_generated_trees() {
+        let mut rng = Lcg::new(7);
+        for _ in 0..200 {
+            let tree = random_tree(&mut rng, 6);
+            let text = serialize(&tree);
+            let parsed = parse(&text).unwrap();
+            assert_eq!(serialize(&parsed), text);
+            assert_eq!(part1(&parsed), part1(&tree));
+        }
+    }
+
+    #[test]
+    fn round_trips_a_deep_chain() {
+        let depth = 200_000;
+        let mut tree = Node::new(Vec::new(), vec![1]);
+        for _ in 1..depth {
+            tree = Node::new(vec![tree], vec![1]);
         }
+        let text = serialize(&tree);
+        let parsed = parse(&text).unwrap();
+        assert_eq!(serialize(&parsed), text);
+        assert_eq!(TreeView::new(&parsed).depth(), depth);
+        assert_eq!((part1(&parsed), part2(&parsed)), (depth as u32, 1));
+    }
+
+    fn error(input: &str) -> ParseError {
+        parse(input).expect_err("The input should be rejected")
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error("1 1 x 2"), ParseError::InvalidNumber { position: 2, text: "x".to_string() });
+        assert_eq!(error("0 1 -5"), ParseError::InvalidNumber { position: 2, text: "-5".to_string() });
+        assert_eq!(error(""), ParseError::MissingHeader { position: 0 });
+        assert_eq!(error("1"), ParseError::MissingHeader { position: 1 });
+        assert_eq!(error("1 1 0"), ParseError::MissingHeader { position: 3 });
+        assert_eq!(error("0 3 1 2"), ParseError::MissingMetadata { position: 4, node: 0, expected: 3 });
+        // The node is the position of the child's header
+        assert_eq!(error("1 1 0 2 5"), ParseError::MissingMetadata { position: 5, node: 2, expected: 2 });
     }
 
-    value(input)
+    #[test]
+    fn rejects_trailing_numbers() {
+        let trailing = error("0 1 5 7 8");
+        assert_eq!(trailing, ParseError::TrailingNumbers { position: 3, count: 2 });
+        assert_eq!(trailing.to_string(), "number 3: 2 numbers left after the root node");
+        assert_eq!(error(&format!("{} 0", EXAMPLE)), ParseError::TrailingNumbers { position: 16, count: 1 });
+    }
+
+    #[test]
+    fn tree_view_of_the_example() {
+        // A has childs B and C, C has the child D with the metadata 99
+        let tree = input_generator(EXAMPLE);
+        let view = TreeView::new(&tree);
+        assert_eq!((view.value(0), view.node_count(), view.depth()), (66, 4, 3));
+        assert_eq!((0..4).map(|id| view.value(id)).collect::<Vec<_>>(), [66, 33, 0, 99]);
+        assert_eq!((0..4).map(|id| view.node_depth(id)).collect::<Vec<_>>(), [0, 1, 1, 2]);
+        assert_eq!(view.node(3).metadata(), [99]);
+        assert_eq!(view.path(3), [0, 2, 3]);
+        assert_eq!(view.path(0), [0]);
+        assert_eq!(
+            view.render_indented(),
+            "#0 metadata [1, 1, 2] value 66\n  #1 metadata [10, 11, 12] value 33\n  #2 metadata [2] value 0\n    #3 metadata [99] value 99\n"
+        );
+    }
+
+    #[test]
+    fn reports_invalid_references() {
+        let tree = input_generator(EXAMPLE);
+        let invalid = TreeView::new(&tree).invalid_references();
+        // C refers to a second child it doesn't have, the references of A are all valid
+        assert_eq!(invalid, [InvalidReference { node: 2, entry: 0, reference: 2, child_count: 1 }]);
+        assert_eq!(invalid[0].to_string(), "node #2, metadata entry 0: refers to child 2 but the node has 1 childs");
+
+        let tree = input_generator("1 3 0 1 7 0 1 2");
+        let entries = TreeView::new(&tree).invalid_references().iter().map(|invalid| invalid.entry).collect::<Vec<_>>();
+        assert_eq!(entries, [0, 2]);
+    }
 }
@@ -1,5 +1,6 @@
 #![allow(unused_imports, unused_variables)]
 use super::prelude::*;
+use std::fmt;
 type Input = (u32, u32);
 
 pub fn input_generator(input: &str) -> Input {
@@ -19,29 +20,219 @@ pub fn input_generator(input: &str) -> Input {
     (players, last_worth)
 }
 
-fn winning_score(players: u32, last_worth: u32) -> u32 {
-    let mut scores = vec![0u32; players as usize];
-    let mut table = VecDeque::new();
-    table.push_back(0);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rules {
+    // Marbles multiple of this are kept by the player instead of being placed
+    pub special: u32,
+    // How many marbles counter-clockwise of the current one is the marble taken with a special one
+    pub jump_back: u32,
+    // New marbles are placed right after the marble this many positions clockwise of the current one
+    pub placement: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self { special: 23, jump_back: 7, placement: 1 }
+    }
+}
+
+// Circular doubly linked list stored in two arrays indexed by marble, every marble is
+// placed at most once so its value is also its slot
+pub struct Game {
+    rules: Rules,
+    next: Vec<u32>,
+    prev: Vec<u32>,
+    current: u32,
+    // Marble the circle is listed from: 0, until it's taken
+    start: u32,
+    last_marble: u32,
+    scores: Vec<u64>,
+}
 
-    for i in 1..(last_worth + 1) {
-        if i % 23 == 0 {
-            table.rotate_right(7);
-            scores[(i % players) as usize] += i + table.pop_back().unwrap();
-            table.rotate_left(1);
+impl Game {
+    pub fn new(players: u32, rules: Rules) -> Self {
+        assert!(players > 0, "There must be at least one player");
+        assert!(rules.special > 1, "With every marble special the circle would be emptied");
+        Self {
+            rules,
+            next: vec![0],
+            prev: vec![0],
+            current: 0,
+            start: 0,
+            last_marble: 0,
+            scores: vec![0; players as usize],
+        }
+    }
+
+    fn clockwise(&self, from: u32, steps: u32) -> u32 {
+        (0..steps).fold(from, |marble, _| self.next[marble as usize])
+    }
+
+    fn counter_clockwise(&self, from: u32, steps: u32) -> u32 {
+        (0..steps).fold(from, |marble, _| self.prev[marble as usize])
+    }
+
+    pub fn play_marble(&mut self) {
+        let marble = self.last_marble + 1;
+        self.last_marble = marble;
+        self.next.push(marble);
+        self.prev.push(marble);
+
+        if marble % self.rules.special == 0 {
+            let removed = self.counter_clockwise(self.current, self.rules.jump_back);
+            let (before, after) = (self.prev[removed as usize], self.next[removed as usize]);
+            self.next[before as usize] = after;
+            self.prev[after as usize] = before;
+            self.current = after;
+            if removed == self.start {
+                self.start = after;
+            }
+
+            let player = (marble - 1) as usize % self.scores.len();
+            self.scores[player] += marble as u64 + removed as u64;
         } else {
-            table.rotate_left(1);
-            table.push_back(i);
+            let before = self.clockwise(self.current, self.rules.placement);
+            let after = self.next[before as usize];
+            self.next[before as usize] = marble;
+            self.prev[after as usize] = marble;
+            self.next[marble as usize] = after;
+            self.prev[marble as usize] = before;
+            self.current = marble;
+        }
+    }
+
+    pub fn play_until(&mut self, last_marble: u32) {
+        while self.last_marble < last_marble {
+            self.play_marble();
         }
     }
 
-    scores.iter().copied().max().unwrap()
+    #[allow(dead_code)]
+    pub fn last_marble(&self) -> u32 { self.last_marble }
+
+    #[allow(dead_code)]
+    pub fn current(&self) -> u32 { self.current }
+
+    // Score of each player, the first one plays marble 1
+    #[allow(dead_code)]
+    pub fn scores(&self) -> &[u64] { &self.scores }
+
+    pub fn winning_score(&self) -> u64 {
+        self.scores.iter().copied().max().unwrap_or(0)
+    }
+
+    // Marbles in the circle clockwise, starting from marble 0 while it's there
+    pub fn circle(&self) -> Vec<u32> {
+        let mut circle = vec![self.start];
+        let mut marble = self.next[self.start as usize];
+        while marble != self.start {
+            circle.push(marble);
+            marble = self.next[marble as usize];
+        }
+        circle
+    }
+}
+
+// The circle in the same format as the puzzle's example, with the last player and the
+// current marble in parentheses. Marbles are right aligned in 3 columns, and the closing
+// parenthesis takes the place of the space before the next marble.
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.last_marble {
+            0 => write!(f, "[-]")?,
+            marble => write!(f, "[{}]", (marble - 1) as usize % self.scores.len() + 1)?,
+        }
+        let mut after_current = false;
+        for marble in self.circle() {
+            let text = if marble == self.current { format!("({}", marble) } else { marble.to_string() };
+            if after_current {
+                write!(f, "){:>2}", text)?;
+            } else {
+                write!(f, "{:>3}", text)?;
+            }
+            after_current = marble == self.current;
+        }
+        if after_current {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn play(players: u32, last_marble: u32, rules: Rules) -> Game {
+    let mut game = Game::new(players, rules);
+    game.play_until(last_marble);
+    game
+}
+
+pub fn part1(&(players, last_worth): &Input) -> u64 {
+    play(players, last_worth, Rules::default()).winning_score()
 }
 
-pub fn part1(&(players, last_worth): &Input) -> u32 {
-    winning_score(players, last_worth)
+pub fn part2(&(players, last_worth): &Input) -> u64 {
+    play(players, last_worth * 100, Rules::default()).winning_score()
 }
 
-pub fn part2(&(players, last_worth): &Input) -> u32 {
-    winning_score(players, last_worth * 100)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_circles() {
+        let expected = "\
+[-] (0)
+[1]  0 (1)
+[2]  0 (2) 1
+[3]  0  2  1 (3)
+[4]  0 (4) 2  1  3
+[5]  0  4  2 (5) 1  3
+[6]  0  4  2  5  1 (6) 3
+[7]  0  4  2  5  1  6  3 (7)
+[8]  0 (8) 4  2  5  1  6  3  7
+[9]  0  8  4 (9) 2  5  1  6  3  7
+[1]  0  8  4  9  2(10) 5  1  6  3  7
+[2]  0  8  4  9  2 10  5(11) 1  6  3  7
+[3]  0  8  4  9  2 10  5 11  1(12) 6  3  7
+[4]  0  8  4  9  2 10  5 11  1 12  6(13) 3  7
+[5]  0  8  4  9  2 10  5 11  1 12  6 13  3(14) 7
+[6]  0  8  4  9  2 10  5 11  1 12  6 13  3 14  7(15)
+[7]  0(16) 8  4  9  2 10  5 11  1 12  6 13  3 14  7 15
+[8]  0 16  8(17) 4  9  2 10  5 11  1 12  6 13  3 14  7 15
+[9]  0 16  8 17  4(18) 9  2 10  5 11  1 12  6 13  3 14  7 15
+[1]  0 16  8 17  4 18  9(19) 2 10  5 11  1 12  6 13  3 14  7 15
+[2]  0 16  8 17  4 18  9 19  2(20)10  5 11  1 12  6 13  3 14  7 15
+[3]  0 16  8 17  4 18  9 19  2 20 10(21) 5 11  1 12  6 13  3 14  7 15
+[4]  0 16  8 17  4 18  9 19  2 20 10 21  5(22)11  1 12  6 13  3 14  7 15
+[5]  0 16  8 17  4 18(19) 2 20 10 21  5 22 11  1 12  6 13  3 14  7 15
+[6]  0 16  8 17  4 18 19  2(24)20 10 21  5 22 11  1 12  6 13  3 14  7 15
+[7]  0 16  8 17  4 18 19  2 24 20(25)10 21  5 22 11  1 12  6 13  3 14  7 15";
+
+        let mut game = Game::new(9, Rules::default());
+        let mut circles = vec![game.to_string()];
+        while game.last_marble() < 25 {
+            game.play_marble();
+            circles.push(game.to_string());
+        }
+        assert_eq!(circles, expected.lines().collect::<Vec<_>>());
+        assert_eq!(game.current(), 25);
+
+        // Only the fifth player scored, with marbles 23 and 9
+        let mut scores = [0; 9];
+        scores[4] = 32;
+        assert_eq!(game.scores(), scores);
+        assert_eq!(game.winning_score(), 32);
+    }
+
+    #[test]
+    fn example_high_scores() {
+        let examples = [(9, 25, 32), (10, 1618, 8317), (13, 7999, 146_373), (17, 1104, 2764), (21, 6111, 54_718), (30, 5807, 37_305)];
+        for &(players, last_marble, score) in examples.iter() {
+            assert_eq!(part1(&(players, last_marble)), score, "{} players, last marble {}", players, last_marble);
+        }
+    }
+
+    #[test]
+    fn parses_the_input_sentence() {
+        assert_eq!(input_generator("10 players; last marble is worth 1618 points"), (10, 1618));
+    }
 }
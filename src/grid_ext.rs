@@ -0,0 +1,175 @@
+use super::prelude::*;
+use std::fmt;
+
+type Point = (usize, usize);
+
+// Offsets are (dx, dy) and listed in reading order
+const OFFSETS_4: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const OFFSETS_8: [(isize, isize); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1, 0),           (1, 0),
+    (-1, 1),  (0, 1),  (1, 1),
+];
+
+pub trait GridExt<T> {
+    fn from_char_map(input: &str, f: impl FnMut(char) -> T) -> Self;
+    fn from_char_map_enumerated(input: &str, f: impl FnMut(Point, char) -> T) -> Self;
+    fn offset(&self, point: Point, offset: (isize, isize)) -> Option<Point>;
+    fn neighbours4(&self, point: Point) -> Neighbours;
+    fn neighbours8(&self, point: Point) -> Neighbours;
+    fn row(&self, y: usize) -> &[T];
+    // No day walks the grid by columns yet
+    #[allow(dead_code)]
+    fn column(&self, x: usize) -> iter::StepBy<std::slice::Iter<'_, T>>;
+    fn display_with<F: Fn(&T) -> char>(&self, f: F) -> GridDisplay<'_, T, F>;
+}
+
+impl<T> GridExt<T> for Grid<T> {
+    fn from_char_map(input: &str, mut f: impl FnMut(char) -> T) -> Self {
+        Self::from_char_map_enumerated(input, |_, c| f(c))
+    }
+
+    fn from_char_map_enumerated(input: &str, mut f: impl FnMut(Point, char) -> T) -> Self {
+        let width = input.lines().next().expect("Invalid input").chars().count();
+        let mut vec = Vec::with_capacity(width * input.lines().count());
+
+        for (y, line) in input.lines().enumerate() {
+            let len_before = vec.len();
+            vec.extend(line.chars().enumerate().map(|(x, c)| f((x, y), c)));
+            assert_eq!(vec.len() - len_before, width, "Invalid input: line {} has the wrong length", y + 1);
+        }
+
+        Grid { vec, width }
+    }
+
+    fn offset(&self, (x, y): Point, (dx, dy): (isize, isize)) -> Option<Point> {
+        let x = (x as isize).checked_add(dx)?.try_into().ok()?;
+        let y = (y as isize).checked_add(dy)?.try_into().ok()?;
+        if x < self.width && y < self.height() { Some((x, y)) } else { None }
+    }
+
+    fn neighbours4(&self, point: Point) -> Neighbours {
+        Neighbours::new(point, self.width, self.height(), &OFFSETS_4)
+    }
+
+    fn neighbours8(&self, point: Point) -> Neighbours {
+        Neighbours::new(point, self.width, self.height(), &OFFSETS_8)
+    }
+
+    fn row(&self, y: usize) -> &[T] {
+        &self.vec[y * self.width..(y + 1) * self.width]
+    }
+
+    fn column(&self, x: usize) -> iter::StepBy<std::slice::Iter<'_, T>> {
+        assert!(x < self.width, "Column {} is out of bounds", x);
+        self.vec[x..].iter().step_by(self.width)
+    }
+
+    fn display_with<F: Fn(&T) -> char>(&self, f: F) -> GridDisplay<'_, T, F> {
+        GridDisplay { grid: self, f }
+    }
+}
+
+pub struct Neighbours {
+    point: Point,
+    width: usize,
+    height: usize,
+    offsets: std::slice::Iter<'static, (isize, isize)>,
+}
+
+impl Neighbours {
+    fn new(point: Point, width: usize, height: usize, offsets: &'static [(isize, isize)]) -> Self {
+        Self { point, width, height, offsets: offsets.iter() }
+    }
+}
+
+impl Iterator for Neighbours {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        let ((x, y), width, height) = (self.point, self.width, self.height);
+        self.offsets.find_map(|&(dx, dy)| {
+            let nx = x.wrapping_add(dx as usize);
+            let ny = y.wrapping_add(dy as usize);
+            if nx < width && ny < height { Some((nx, ny)) } else { None }
+        })
+    }
+}
+
+pub struct GridDisplay<'a, T, F> {
+    grid: &'a Grid<T>,
+    f: F,
+}
+
+impl<T, F: Fn(&T) -> char> fmt::Display for GridDisplay<'_, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.grid.vec.chunks(self.grid.width) {
+            for cell in row {
+                write!(f, "{}", (self.f)(cell))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> Grid<char> {
+        Grid::from_char_map("abc\ndef\nghi", |c| c)
+    }
+
+    #[test]
+    fn parses_char_maps() {
+        let grid = Grid::from_char_map_enumerated("ab\ncd\n", |(x, y), c| (x, y, c));
+        assert_eq!(grid.width, 2);
+        assert_eq!(grid.height(), 2);
+        assert_eq!(grid[(1, 0)], (1, 0, 'b'));
+        assert_eq!(grid[(0, 1)], (0, 1, 'c'));
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2 has the wrong length")]
+    fn rejects_ragged_lines() {
+        Grid::from_char_map("abc\nde\nfgh", |c| c);
+    }
+
+    #[test]
+    fn neighbours_are_in_reading_order() {
+        let grid = grid();
+        assert_eq!(grid.neighbours4((1, 1)).collect::<Vec<_>>(), [(1, 0), (0, 1), (2, 1), (1, 2)]);
+        assert_eq!(
+            grid.neighbours8((1, 1)).collect::<Vec<_>>(),
+            [(0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn neighbours_stay_in_bounds() {
+        let grid = grid();
+        assert_eq!(grid.neighbours4((0, 0)).collect::<Vec<_>>(), [(1, 0), (0, 1)]);
+        assert_eq!(grid.neighbours4((2, 2)).collect::<Vec<_>>(), [(2, 1), (1, 2)]);
+        assert_eq!(grid.neighbours4((1, 0)).collect::<Vec<_>>(), [(0, 0), (2, 0), (1, 1)]);
+        assert_eq!(grid.neighbours8((2, 0)).collect::<Vec<_>>(), [(1, 0), (1, 1), (2, 1)]);
+        assert_eq!(grid.neighbours8((0, 2)).collect::<Vec<_>>(), [(0, 1), (1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn offsets_are_checked() {
+        let grid = grid();
+        assert_eq!(grid.offset((0, 0), (-1, 0)), None);
+        assert_eq!(grid.offset((2, 1), (1, 0)), None);
+        assert_eq!(grid.offset((1, 2), (0, 1)), None);
+        assert_eq!(grid.offset((1, 1), (1, -1)), Some((2, 0)));
+    }
+
+    #[test]
+    fn views_and_display() {
+        let grid = grid();
+        assert_eq!(grid.row(1), ['d', 'e', 'f']);
+        assert_eq!(grid.column(2).copied().collect::<String>(), "cfi");
+        assert_eq!(grid.display_with(|c| c.to_ascii_uppercase()).to_string(), "ABC\nDEF\nGHI\n");
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Inputs live either directly in the root as `dayNN.txt`, or one directory
+// per user as `<user>/dayNN.txt`.
+pub struct InputStore {
+    root: PathBuf,
+}
+
+impl InputStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn file_name(day: u32) -> String {
+        format!("day{:02}.txt", day)
+    }
+
+    pub fn users(&self) -> Vec<String> {
+        let mut users = fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+        users.sort();
+        users
+    }
+
+    // Without a user the flat layout is preferred, falling back to the only user if there's one
+    pub fn path(&self, user: Option<&str>, day: u32) -> Result<PathBuf, String> {
+        if let Some(user) = user {
+            return Ok(self.root.join(user).join(Self::file_name(day)));
+        }
+
+        let flat = self.root.join(Self::file_name(day));
+        if flat.exists() {
+            return Ok(flat);
+        }
+        match self.users().as_slice() {
+            [] => Ok(flat),
+            [user] => Ok(self.root.join(user).join(Self::file_name(day))),
+            users => Err(format!(
+                "Missing {}, pick one of the users with --user: {}",
+                flat.display(),
+                users.join(", ")
+            )),
+        }
+    }
+
+    // Inputs aren't downloaded, a missing one has to be saved by hand
+    pub fn read(&self, user: Option<&str>, day: u32) -> Result<String, String> {
+        let path = self.path(user, day)?;
+        if !path.exists() {
+            return Err(format!(
+                "Missing {}, save the input from https://adventofcode.com/2018/day/{}/input there",
+                path.display(),
+                day
+            ));
+        }
+        read_input(&path)
+    }
+}
+
+fn read_input(path: &Path) -> Result<String, String> {
+    let input = fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+    Ok(input.trim_end_matches(&['\n', '\r'][..]).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn save(root: &Path, file: &str, input: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, input).unwrap();
+    }
+
+    #[test]
+    fn prefers_the_flat_layout() {
+        let temp = TempDir::new("inputs-flat");
+        save(temp.path(), "day01.txt", "+1\n");
+        save(temp.path(), "alice/day01.txt", "+2\n");
+        save(temp.path(), "bob/day01.txt", "+3\n");
+        let store = InputStore::new(temp.path());
+        assert_eq!(store.path(None, 1), Ok(temp.path().join("day01.txt")));
+        assert_eq!(store.read(None, 1), Ok("+1".to_string()));
+    }
+
+    #[test]
+    fn falls_back_to_the_only_user() {
+        let temp = TempDir::new("inputs-one-user");
+        save(temp.path(), "alice/day02.txt", "abcde\r\n");
+        let store = InputStore::new(temp.path());
+        assert_eq!(store.users(), ["alice"]);
+        assert_eq!(store.path(None, 2), Ok(temp.path().join("alice").join("day02.txt")));
+        assert_eq!(store.read(None, 2), Ok("abcde".to_string()));
+    }
+
+    #[test]
+    fn several_users_need_a_choice() {
+        let temp = TempDir::new("inputs-users");
+        save(temp.path(), "bob/day03.txt", "#1 @ 1,3: 4x4");
+        save(temp.path(), "alice/day03.txt", "#1 @ 3,1: 4x4");
+        let store = InputStore::new(temp.path());
+        assert_eq!(store.users(), ["alice", "bob"]);
+        let error = store.path(None, 3).unwrap_err();
+        assert!(error.ends_with("pick one of the users with --user: alice, bob"), "{}", error);
+    }
+
+    #[test]
+    fn explicit_user_directory() {
+        let temp = TempDir::new("inputs-explicit");
+        save(temp.path(), "day04.txt", "flat");
+        save(temp.path(), "alice/day04.txt", "alice's");
+        save(temp.path(), "bob/day04.txt", "bob's");
+        let store = InputStore::new(temp.path());
+        assert_eq!(store.path(Some("bob"), 4), Ok(temp.path().join("bob").join("day04.txt")));
+        assert_eq!(store.read(Some("alice"), 4), Ok("alice's".to_string()));
+        // The user's directory is used even when it lacks the day
+        let error = store.read(Some("carol"), 4).unwrap_err();
+        assert!(error.starts_with(&format!("Missing {}", temp.path().join("carol").join("day04.txt").display())), "{}", error);
+    }
+
+    #[test]
+    fn missing_inputs() {
+        let temp = TempDir::new("inputs-missing");
+        let store = InputStore::new(temp.path());
+        assert_eq!(store.users(), Vec::<String>::new());
+        assert_eq!(store.path(None, 25), Ok(temp.path().join("day25.txt")));
+        assert_eq!(
+            store.read(None, 25),
+            Err(format!(
+                "Missing {}, save the input from https://adventofcode.com/2018/day/25/input there",
+                temp.path().join("day25.txt").display()
+            ))
+        );
+        // A root that doesn't exist has no users
+        assert_eq!(InputStore::new(temp.path().join("nowhere")).users(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Int(i128),
+    Str(String),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<'a>(fields: impl IntoIterator<Item = (&'a str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Int(n) => write!(f, "{}", n),
+            Json::Str(s) => write_escaped(f, s),
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i != 0 { write!(f, ",")?; }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        let json = Json::Str("say \"hi\"\\\n\tnow\u{1}".to_string());
+        assert_eq!(json.to_string(), r#""say \"hi\"\\\n\tnow\u0001""#);
+    }
+
+    #[test]
+    fn writes_nested_objects() {
+        let json = Json::object(vec![
+            ("a", Json::Int(-3)),
+            ("b\"", Json::Null),
+            ("c", Json::object(vec![("d", Json::Str("é".to_string()))])),
+            ("e", Json::object(vec![])),
+        ]);
+        assert_eq!(json.to_string(), r#"{"a":-3,"b\"":null,"c":{"d":"é"},"e":{}}"#);
+    }
+}
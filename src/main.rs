@@ -1,27 +1,66 @@
-aoc_helper::main!(2018 => 
-    day1,
-    day2,
-    day3,
-    day4,
-    day5,
-    day6,
-    day7,
-    day8,
-    day9,
-    day10,
-    day11,
-    day12,
-    day13,
-    day14,
-    day15,
-    day16,
-    day17,
-    day18,
-    day19,
-    day20,
-    day21,
-    day22,
-    day23,
-    day24,
-    day25,
-);
+mod prelude {
+    pub use aoc_helper::prelude::*;
+}
+
+mod cycle;
+mod grid_ext;
+mod inputs;
+mod json;
+mod ocr;
+mod render;
+mod runner;
+#[cfg(test)]
+mod test_util;
+
+macro_rules! days {
+    (@visualize $day:ident) => { None };
+    (@visualize $day:ident visualize) => {
+        Some(|input, frames| $day::visualize(&$day::input_generator(input), frames))
+    };
+    ($($day:ident: $n:literal => $($part:ident),+ $(with $visualize:ident)?;)*) => {
+        $(mod $day;)*
+
+        fn main() {
+            runner::main(&[$(
+                runner::Day {
+                    number: $n,
+                    solve: |input| {
+                        let mut report = runner::DayReport::new($n);
+                        let generated = report.parse(|| $day::input_generator(input));
+                        $(report.run(generated.as_ref().map(|generated| move || $day::$part(generated)));)+
+                        report
+                    },
+                    visualize: days!(@visualize $day $($visualize)?),
+                },
+            )*]);
+        }
+    };
+}
+
+days! {
+    day1: 1 => part1, part2;
+    day2: 2 => part1, part2;
+    day3: 3 => part1, part2 with visualize;
+    day4: 4 => part1, part2;
+    day5: 5 => part1, part2;
+    day6: 6 => part1, part2 with visualize;
+    day7: 7 => part1, part2;
+    day8: 8 => part1, part2;
+    day9: 9 => part1, part2;
+    day10: 10 => part1, part2 with visualize;
+    day11: 11 => part1, part2;
+    day12: 12 => part1, part2;
+    day13: 13 => part1, part2 with visualize;
+    day14: 14 => part1, part2;
+    day15: 15 => part1, part2 with visualize;
+    day16: 16 => part1, part2;
+    day17: 17 => part1, part2 with visualize;
+    day18: 18 => part1, part2 with visualize;
+    day19: 19 => part1, part2;
+    day20: 20 => part1, part2 with visualize;
+    day21: 21 => part1, part2;
+    day22: 22 => part1, part2 with visualize;
+    day23: 23 => part1, part2;
+    day24: 24 => part1, part2;
+    day25: 25 => part1;
+}
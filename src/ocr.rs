@@ -0,0 +1,255 @@
+use super::grid_ext::GridExt;
+use super::prelude::*;
+use std::fmt;
+
+// Letters drawn by the puzzles, row by row. The puzzles never showed D M N Q T V W X in the small
+// font nor D I M O Q S T U V W Y in the large one, those are drawn in the style of the others.
+const SMALL_FONT: &[(char, &[&str])] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('D', &["###.", "#..#", "#..#", "#..#", "#..#", "###."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('M', &["#...#", "##.##", "#.#.#", "#...#", "#...#", "#...#"]),
+    ('N', &["#..#", "##.#", "#.##", "#..#", "#..#", "#..#"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('Q', &[".##.", "#..#", "#..#", "#..#", "#.#.", ".#.#"]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('T', &["###.", ".#..", ".#..", ".#..", ".#..", ".#.."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('V', &["#...#", "#...#", "#...#", ".#.#.", ".#.#.", "..#.."]),
+    ('W', &["#...#", "#...#", "#...#", "#.#.#", "##.##", "#...#"]),
+    ('X', &["#..#", "#..#", ".##.", ".##.", "#..#", "#..#"]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_FONT: &[(char, &[&str])] = &[
+    ('A', &["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+    ('B', &["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('C', &[".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+    ('D', &["#####.", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('E', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('F', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('G', &[".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+    ('H', &["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('I', &["..###.", "...#..", "...#..", "...#..", "...#..", "...#..", "...#..", "...#..", "...#..", "..###."]),
+    ('J', &["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+    ('K', &["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+    ('L', &["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('M', &["#....#", "##..##", "#.##.#", "#.##.#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('N', &["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+    ('O', &[".####.", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", ".####."]),
+    ('P', &["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('Q', &[".####.", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#..#.#", "#...#.", ".###.#"]),
+    ('R', &["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+    ('S', &[".####.", "#....#", "#.....", "#.....", ".####.", ".....#", ".....#", ".....#", "#....#", ".####."]),
+    ('T', &["#####.", "..#...", "..#...", "..#...", "..#...", "..#...", "..#...", "..#...", "..#...", "..#..."]),
+    ('U', &["#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#....#", ".####."]),
+    ('V', &["#....#", "#....#", "#....#", "#....#", ".#..#.", ".#..#.", ".#..#.", "..##..", "..##..", "..##.."]),
+    ('W', &["#....#", "#....#", "#....#", "#....#", "#....#", "#....#", "#.##.#", "#.##.#", "##..##", "#....#"]),
+    ('X', &["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+    ('Y', &["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", "..##..", "..##..", "..##..", "..##.."]),
+    ('Z', &["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownGlyph {
+    // Column of the art where the glyph starts
+    pub column: usize,
+    pub art: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OcrError {
+    // There's no font with glyphs as tall as the art
+    UnsupportedHeight { height: usize, art: String },
+    // `text` has a `?` in place of each unknown glyph
+    UnknownGlyphs { text: String, glyphs: Vec<UnknownGlyph> },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight { height, art } => {
+                write!(f, "There's no font {} pixels tall for:\n{}", height, art)
+            }
+            OcrError::UnknownGlyphs { text, glyphs } => {
+                write!(f, "Unknown glyphs in {:?}", text)?;
+                for glyph in glyphs {
+                    write!(f, "\nat column {}:\n{}", glyph.column, glyph.art)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Lit pixels are `#`, anything else is off
+#[allow(dead_code)] // Only the tests read art from text so far
+pub fn parse_art(art: &str) -> Grid<bool> {
+    Grid::from_char_map(art, |c| c == '#')
+}
+
+pub fn render_art(grid: &Grid<bool>) -> String {
+    grid.display_with(|&lit| if lit { '#' } else { '.' }).to_string()
+}
+
+// Rows of the glyph without the blank columns around it
+fn trim_columns(rows: &[String]) -> Vec<String> {
+    let is_blank = |x: usize| rows.iter().all(|row| row.as_bytes()[x] != b'#');
+    let width = rows.first().map_or(0, |row| row.len());
+    match ((0..width).find(|&x| !is_blank(x)), (0..width).rev().find(|&x| !is_blank(x))) {
+        (Some(from), Some(to)) => rows.iter().map(|row| row[from..=to].to_string()).collect(),
+        _ => vec![String::new(); rows.len()],
+    }
+}
+
+fn font(height: usize) -> Option<HashMap<Vec<String>, char>> {
+    let font = match height {
+        6 => SMALL_FONT,
+        10 => LARGE_FONT,
+        _ => return None,
+    };
+    let glyphs = font.iter().map(|&(c, rows)| {
+        let rows = rows.iter().map(|row| row.to_string()).collect::<Vec<_>>();
+        (trim_columns(&rows), c)
+    });
+    Some(glyphs.collect())
+}
+
+// Glyphs are separated by blank columns, blank rows above and below the text are ignored
+pub fn recognize(grid: &Grid<bool>) -> Result<String, OcrError> {
+    let art = render_art(grid);
+    let rows = art
+        .lines()
+        .skip_while(|row| !row.contains('#'))
+        .map(|row| row.to_string())
+        .collect::<Vec<_>>();
+    let height = rows.iter().rposition(|row| row.contains('#')).map_or(0, |last| last + 1);
+    let rows = &rows[..height];
+    let font = font(height).ok_or_else(|| OcrError::UnsupportedHeight { height, art: art.clone() })?;
+
+    let width = grid.width;
+    let lit_column = |x: usize| rows.iter().any(|row| row.as_bytes()[x] == b'#');
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    let mut x = 0;
+    while x < width {
+        if !lit_column(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < width && lit_column(x) {
+            x += 1;
+        }
+        let glyph = rows.iter().map(|row| row[start..x].to_string()).collect::<Vec<_>>();
+        match font.get(&glyph) {
+            Some(&c) => text.push(c),
+            None => {
+                text.push('?');
+                unknown.push(UnknownGlyph { column: start, art: glyph.join("\n") });
+            }
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs { text, glyphs: unknown })
+    }
+}
+
+// The recognized text, or the art itself on a new line when it can't be read
+pub fn recognize_or_art(grid: &Grid<bool>) -> String {
+    recognize(grid).unwrap_or_else(|_| format!("\n{}", render_art(grid)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The glyphs of `text` side by side, one blank column apart, with a blank row above and below
+    fn art(font: &[(char, &[&str])], text: &str) -> String {
+        let glyphs = text.chars().map(|c| font.iter().find(|&&(glyph, _)| glyph == c).unwrap().1).collect::<Vec<_>>();
+        let rows = (0..glyphs[0].len()).map(|y| glyphs.iter().map(|glyph| glyph[y]).join("."));
+        let rows = rows.collect::<Vec<_>>();
+        let blank = ".".repeat(rows[0].len());
+        iter::once(blank.clone()).chain(rows).chain(iter::once(blank)).join("\n")
+    }
+
+    #[test]
+    fn recognizes_small_letters() {
+        let grid = parse_art(
+            "\
+.##..###..####.
+#..#.#..#.#....
+#..#.###..###..
+####.#..#.#....
+#..#.#..#.#....
+#..#.###..####.",
+        );
+        assert_eq!(recognize(&grid), Ok("ABE".to_string()));
+    }
+
+    #[test]
+    fn recognizes_large_letters() {
+        let grid = parse_art(
+            "\
+#....#..######
+#....#.......#
+#....#.......#
+#....#......#.
+######.....#..
+#....#....#...
+#....#...#....
+#....#..#.....
+#....#..#.....
+#....#..######",
+        );
+        assert_eq!(recognize(&grid), Ok("HZ".to_string()));
+    }
+
+    #[test]
+    fn recognizes_every_glyph() {
+        for &font in [SMALL_FONT, LARGE_FONT].iter() {
+            let text = font.iter().map(|&(c, _)| c).collect::<String>();
+            assert_eq!(text, "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+            assert_eq!(recognize(&parse_art(&art(font, &text))), Ok(text));
+        }
+    }
+
+    #[test]
+    fn reports_unknown_glyphs() {
+        let grid = parse_art(
+            "\
+#####..##.
+#...#.#..#
+#...#.#..#
+#...#.####
+#...#.#..#
+#####.#..#",
+        );
+        let glyph = UnknownGlyph { column: 0, art: "#####\n#...#\n#...#\n#...#\n#...#\n#####".to_string() };
+        let error = OcrError::UnknownGlyphs { text: "?A".to_string(), glyphs: vec![glyph] };
+        assert_eq!(recognize(&grid), Err(error.clone()));
+        assert!(error.to_string().starts_with("Unknown glyphs in \"?A\"\nat column 0:\n#####\n#...#\n"));
+        assert_eq!(recognize_or_art(&grid), format!("\n{}", render_art(&grid)));
+    }
+
+    #[test]
+    fn rejects_unsupported_heights() {
+        let grid = parse_art("#\n#\n#");
+        assert_eq!(recognize(&grid), Err(OcrError::UnsupportedHeight { height: 3, art: render_art(&grid) }));
+    }
+}
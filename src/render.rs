@@ -0,0 +1,254 @@
+use super::prelude::*;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const GRAY: Rgb = Rgb(128, 128, 128);
+    pub const RED: Rgb = Rgb(220, 50, 47);
+    pub const GREEN: Rgb = Rgb(80, 160, 40);
+    pub const BLUE: Rgb = Rgb(38, 139, 210);
+    pub const YELLOW: Rgb = Rgb(220, 180, 0);
+    pub const BROWN: Rgb = Rgb(140, 90, 40);
+
+    fn luma(self) -> u8 {
+        ((self.0 as u32 * 299 + self.1 as u32 * 587 + self.2 as u32 * 114) / 1000) as u8
+    }
+
+    // Linear interpolation between two colors, `t` goes from 0 to 255
+    pub fn mix(self, other: Rgb, t: u8) -> Rgb {
+        let lerp = |a: u8, b: u8| ((a as u32 * (255 - t as u32) + b as u32 * t as u32) / 255) as u8;
+        Rgb(lerp(self.0, other.0), lerp(self.1, other.1), lerp(self.2, other.2))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pixel {
+    pub ch: char,
+    pub color: Rgb,
+}
+
+impl Pixel {
+    pub fn new(ch: char, color: Rgb) -> Self { Self { ch, color } }
+}
+
+pub trait Render {
+    fn size(&self) -> (usize, usize);
+    fn pixel(&self, x: usize, y: usize) -> Pixel;
+}
+
+// Adapter for rendering a `Grid` through a per-cell mapping
+pub struct GridRender<'a, T, F> {
+    grid: &'a Grid<T>,
+    f: F,
+}
+
+pub fn from_grid<T, F: Fn(&T) -> Pixel>(grid: &Grid<T>, f: F) -> GridRender<'_, T, F> {
+    GridRender { grid, f }
+}
+
+impl<T, F: Fn(&T) -> Pixel> Render for GridRender<'_, T, F> {
+    fn size(&self) -> (usize, usize) { (self.grid.width, self.grid.height()) }
+    fn pixel(&self, x: usize, y: usize) -> Pixel { (self.f)(&self.grid[(x, y)]) }
+}
+
+// Owned pixel buffer for states that aren't stored as a `Grid`
+pub struct Canvas {
+    pixels: Grid<Pixel>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: Pixel) -> Self {
+        Self { pixels: Grid { vec: vec![background; width * height], width } }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, pixel: Pixel) {
+        if x < self.pixels.width && y < self.pixels.height() {
+            self.pixels[(x, y)] = pixel;
+        }
+    }
+}
+
+impl Render for Canvas {
+    fn size(&self) -> (usize, usize) { (self.pixels.width, self.pixels.height()) }
+    fn pixel(&self, x: usize, y: usize) -> Pixel { self.pixels[(x, y)] }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, FromStr)]
+#[display(style = "lowercase")]
+pub enum Backend {
+    Ascii,
+    Ansi,
+    Ppm,
+    Pgm,
+}
+
+impl Backend {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Backend::Ascii => "txt",
+            Backend::Ansi => "ans",
+            Backend::Ppm => "ppm",
+            Backend::Pgm => "pgm",
+        }
+    }
+
+    pub fn write(self, frame: &impl Render, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = frame.size();
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        match self {
+            Backend::Ascii | Backend::Ansi => {
+                for y in 0..height {
+                    for x in 0..width {
+                        let Pixel { ch, color: Rgb(r, g, b) } = frame.pixel(x, y);
+                        if self == Backend::Ansi {
+                            write!(out, "\x1b[38;2;{};{};{}m{}", r, g, b, ch)?;
+                        } else {
+                            write!(out, "{}", ch)?;
+                        }
+                    }
+                    if self == Backend::Ansi {
+                        write!(out, "\x1b[0m")?;
+                    }
+                    writeln!(out)?;
+                }
+            }
+            Backend::Ppm => {
+                write!(out, "P6\n{} {}\n255\n", width, height)?;
+                for (x, y) in pixels {
+                    let Rgb(r, g, b) = frame.pixel(x, y).color;
+                    out.write_all(&[r, g, b])?;
+                }
+            }
+            Backend::Pgm => {
+                write!(out, "P5\n{} {}\n255\n", width, height)?;
+                for (x, y) in pixels {
+                    out.write_all(&[frame.pixel(x, y).color.luma()])?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Writes each pushed frame to `<dir>/<number>.<ext>`, numbered from 0
+pub struct Frames {
+    dir: PathBuf,
+    backend: Backend,
+    count: usize,
+    ticks: Option<usize>,
+}
+
+impl Frames {
+    pub fn create(dir: impl AsRef<Path>, backend: Backend) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self { dir: dir.as_ref().to_path_buf(), backend, count: 0, ticks: None })
+    }
+
+    // How many ticks to render for days whose state evolves forever
+    pub fn with_ticks(self, ticks: Option<usize>) -> Self {
+        Self { ticks, ..self }
+    }
+
+    pub fn ticks(&self) -> Option<usize> { self.ticks }
+
+    pub fn push(&mut self, frame: &impl Render) -> io::Result<()> {
+        let path = self.dir.join(format!("{:06}.{}", self.count, self.backend.extension()));
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        self.backend.write(frame, &mut out)?;
+        out.flush()?;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    // 2x2 checkerboard, the bottom right pixel is red
+    fn checkerboard() -> Canvas {
+        let mut canvas = Canvas::new(2, 2, Pixel::new('.', Rgb::BLACK));
+        canvas.set(1, 0, Pixel::new('#', Rgb::WHITE));
+        canvas.set(0, 1, Pixel::new('#', Rgb::WHITE));
+        canvas.set(1, 1, Pixel::new('r', Rgb::RED));
+        // Out of bounds pixels are ignored
+        canvas.set(2, 0, Pixel::new('x', Rgb::GREEN));
+        canvas
+    }
+
+    fn written(backend: Backend, frame: &impl Render) -> Vec<u8> {
+        let mut out = Vec::new();
+        backend.write(frame, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn ascii_is_the_characters() {
+        assert_eq!(written(Backend::Ascii, &checkerboard()), b".#\n#r\n");
+    }
+
+    #[test]
+    fn ansi_colors_each_character_and_resets_each_line() {
+        let expected = "\
+\x1b[38;2;0;0;0m.\x1b[38;2;255;255;255m#\x1b[0m
+\x1b[38;2;255;255;255m#\x1b[38;2;220;50;47mr\x1b[0m
+";
+        assert_eq!(String::from_utf8(written(Backend::Ansi, &checkerboard())).unwrap(), expected);
+    }
+
+    #[test]
+    fn ppm_is_a_header_and_rgb_triples_by_row() {
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 255, 255, 255, 255, 255, 255, 220, 50, 47]);
+        assert_eq!(written(Backend::Ppm, &checkerboard()), expected);
+    }
+
+    #[test]
+    fn pgm_is_a_header_and_one_luma_byte_per_pixel() {
+        // Red is (220 * 299 + 50 * 587 + 47 * 114) / 1000
+        let mut expected = b"P5\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[0, 255, 255, 100]);
+        assert_eq!(written(Backend::Pgm, &checkerboard()), expected);
+    }
+
+    #[test]
+    fn non_square_frames_keep_their_size() {
+        let grid = Grid { vec: vec![true, false, false, true, true, false], width: 3 };
+        let frame = from_grid(&grid, |&lit| Pixel::new(if lit { '#' } else { '.' }, if lit { Rgb::WHITE } else { Rgb::BLACK }));
+        assert_eq!(written(Backend::Ascii, &frame), b"#..\n##.\n");
+        assert_eq!(written(Backend::Pgm, &frame), b"P5\n3 2\n255\n\xff\0\0\xff\xff\0");
+    }
+
+    #[test]
+    fn mixes_colors() {
+        assert_eq!(Rgb::BLACK.mix(Rgb::WHITE, 0), Rgb::BLACK);
+        assert_eq!(Rgb::BLACK.mix(Rgb::WHITE, 255), Rgb::WHITE);
+        assert_eq!(Rgb::BLACK.mix(Rgb::WHITE, 128), Rgb::GRAY);
+    }
+
+    #[test]
+    fn frames_are_numbered_from_zero() {
+        let temp = TempDir::new("frames");
+        let dir = temp.path().join("day03");
+        let mut frames = Frames::create(&dir, Backend::Ppm).unwrap().with_ticks(Some(5));
+        assert_eq!(frames.ticks(), Some(5));
+        for _ in 0..3 {
+            frames.push(&checkerboard()).unwrap();
+        }
+
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["000000.ppm", "000001.ppm", "000002.ppm"]);
+        assert_eq!(fs::read(dir.join("000001.ppm")).unwrap(), written(Backend::Ppm, &checkerboard()));
+    }
+}
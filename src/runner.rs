@@ -0,0 +1,389 @@
+use super::inputs::InputStore;
+use super::json::Json;
+use super::prelude::*;
+use super::render::{Backend, Frames};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub trait Answer {
+    fn text(&self) -> String;
+    fn value(&self) -> Json;
+}
+
+macro_rules! impl_answer_int {
+    ($($t:ty),*) => {
+        $(impl Answer for $t {
+            fn text(&self) -> String { self.to_string() }
+            fn value(&self) -> Json { Json::Int(*self as i128) }
+        })*
+    };
+}
+impl_answer_int!(i32, i64, isize, u16, u32, u64, usize);
+
+impl Answer for String {
+    fn text(&self) -> String { self.clone() }
+    fn value(&self) -> Json { Json::Str(self.clone()) }
+}
+
+impl<T: Answer> Answer for Option<T> {
+    fn text(&self) -> String { self.as_ref().map_or_else(|| "none".to_string(), T::text) }
+    fn value(&self) -> Json { self.as_ref().map_or(Json::Null, T::value) }
+}
+
+pub struct Day {
+    pub number: u32,
+    pub solve: fn(&str) -> DayReport,
+    pub visualize: Option<fn(&str, &mut Frames) -> io::Result<()>>,
+}
+
+pub struct PartReport {
+    pub part: usize,
+    pub answer: Result<(String, Json), String>,
+    pub solve_time: Option<Duration>,
+}
+
+pub struct DayReport {
+    pub day: u32,
+    pub parse_time: Option<Duration>,
+    pub parse_error: Option<String>,
+    pub parts: Vec<PartReport>,
+}
+
+// Whether a solver is running inside `timed`, whose panics are caught and reported as errors.
+// Not thread local, since the panics of the solvers' worker threads reach `timed` too.
+static CATCHING: AtomicBool = AtomicBool::new(false);
+
+// Silences the panics caught by `timed`, any other panic still goes through the previous hook
+fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !CATCHING.load(Ordering::SeqCst) {
+            previous(info);
+        }
+    }));
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (Result<T, String>, Duration) {
+    let start = Instant::now();
+    CATCHING.store(true, Ordering::SeqCst);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.store(false, Ordering::SeqCst);
+    let result = result.map_err(|payload| {
+        payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown panic".to_string())
+    });
+    (result, start.elapsed())
+}
+
+impl DayReport {
+    pub fn new(day: u32) -> Self {
+        Self { day, parse_time: None, parse_error: None, parts: Vec::new() }
+    }
+
+    fn failed(day: u32, error: String) -> Self {
+        Self { parse_error: Some(error), ..Self::new(day) }
+    }
+
+    pub fn parse<I>(&mut self, generator: impl FnOnce() -> I) -> Option<I> {
+        let (result, time) = timed(generator);
+        self.parse_time = Some(time);
+        result.map_err(|e| self.parse_error = Some(e)).ok()
+    }
+
+    pub fn run<A: Answer>(&mut self, solver: Option<impl FnOnce() -> A>) {
+        let part = self.parts.len() + 1;
+        let (answer, solve_time) = match solver {
+            Some(solver) => {
+                let (result, time) = timed(solver);
+                (result.map(|answer| (answer.text(), answer.value())), Some(time))
+            }
+            None => (Err("The input generator failed".to_string()), None),
+        };
+        self.parts.push(PartReport { part, answer, solve_time });
+    }
+}
+
+fn duration_ns(duration: Option<Duration>) -> Json {
+    duration.map_or(Json::Null, |d| Json::Int(d.as_nanos() as i128))
+}
+
+fn print_text(report: &DayReport) {
+    println!("Day {}", report.day);
+    if let Some(time) = report.parse_time {
+        println!("  Generator: {:?}", time);
+    }
+    if let Some(error) = &report.parse_error {
+        println!("  Error: {}", error);
+        return;
+    }
+    for part in report.parts.iter() {
+        match (&part.answer, part.solve_time) {
+            (Ok((text, _)), Some(time)) => println!("  Part {}: {} ({:?})", part.part, text, time),
+            (Ok((text, _)), None) => println!("  Part {}: {}", part.part, text),
+            (Err(error), _) => println!("  Part {}: error: {}", part.part, error),
+        }
+    }
+}
+
+// One JSON object per line, for each part of the day. A day whose input couldn't be
+// loaded or parsed gets a single record with a null part.
+fn json_records(report: &DayReport, user: Option<&str>) -> Vec<Json> {
+    let record = |part: Json, answer: Json, value: Json, solve_time: Option<Duration>, error: Option<&String>| {
+        Json::object(vec![
+            ("user", user.map_or(Json::Null, |u| Json::Str(u.to_string()))),
+            ("day", Json::Int(report.day as i128)),
+            ("part", part),
+            ("answer", answer),
+            ("value", value),
+            ("parse_time_ns", duration_ns(report.parse_time)),
+            ("solve_time_ns", duration_ns(solve_time)),
+            ("error", error.map_or(Json::Null, |e| Json::Str(e.clone()))),
+        ])
+    };
+
+    if report.parts.is_empty() {
+        return vec![record(Json::Null, Json::Null, Json::Null, None, report.parse_error.as_ref())];
+    }
+    report.parts.iter().map(|part| {
+        let part_number = Json::Int(part.part as i128);
+        match &part.answer {
+            Ok((text, value)) => record(part_number, Json::Str(text.clone()), value.clone(), part.solve_time, None),
+            Err(error) => record(part_number, Json::Null, Json::Null, part.solve_time, Some(error)),
+        }
+    }).collect()
+}
+
+fn print_json(report: &DayReport, user: Option<&str>) {
+    for record in json_records(report, user) {
+        println!("{}", record);
+    }
+}
+
+// Answers of every user side by side, one row for each part
+fn print_table(users: &[String], reports: &[Vec<DayReport>]) {
+    let mut rows = BTreeMap::<(u32, usize), Vec<String>>::new();
+    let mut errors = Vec::new();
+
+    for (user_idx, (user, user_reports)) in users.iter().zip(reports).enumerate() {
+        for report in user_reports {
+            if let Some(error) = &report.parse_error {
+                errors.push(format!("{}, day {}: {}", user, report.day, error));
+            }
+            for part in report.parts.iter() {
+                let cell = match &part.answer {
+                    Ok((text, _)) => text.clone(),
+                    Err(error) => {
+                        if report.parse_error.is_none() {
+                            errors.push(format!("{}, day {} part {}: {}", user, report.day, part.part, error));
+                        }
+                        "error".to_string()
+                    }
+                };
+                rows.entry((report.day, part.part)).or_insert_with(|| vec!["-".to_string(); users.len()])[user_idx] = cell;
+            }
+        }
+    }
+
+    let widths = users.iter()
+        .enumerate()
+        .map(|(idx, user)| rows.values().map(|row| row[idx].chars().count()).fold(user.chars().count(), max))
+        .collect::<Vec<_>>();
+
+    let print_row = |first: String, cells: &mut dyn Iterator<Item = &String>| {
+        let line = cells.zip(&widths).fold(first, |line, (cell, width)| format!("{}  {:<width$}", line, cell, width = width));
+        println!("{}", line.trim_end());
+    };
+    print_row("Day  Part".to_string(), &mut users.iter());
+    for ((day, part), row) in rows.iter() {
+        print_row(format!("{:>3}  {:>4}", day, part), &mut row.iter());
+    }
+
+    if !errors.is_empty() {
+        println!();
+        for error in errors {
+            println!("{}", error);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format { Text, Json }
+
+enum Users {
+    Default,
+    One(String),
+    All,
+}
+
+struct Options {
+    format: Format,
+    users: Users,
+    visualize: Option<(PathBuf, Backend)>,
+    ticks: Option<usize>,
+    days: Vec<u32>,
+}
+
+const USAGE: &str = "\
+Usage: adventofcode-2018 [OPTIONS] [DAY...]
+
+Reads the input of each day from inputs/dayNN.txt, or inputs/<user>/dayNN.txt
+when inputs are stored per user, and runs all of its parts.
+
+Options:
+  --user <NAME>                   Use the inputs in inputs/NAME
+  --all-users                     Run the inputs of every user and tabulate the answers
+  --format <text|json>            Output format, json prints one record per line [default: text]
+  --visualize <DIR>               Dump the rendered frames of each day to DIR/[user/]dayNN
+  --backend <ascii|ansi|ppm|pgm>  Frame format used by --visualize [default: ascii]
+  --ticks <N>                     Ticks or sampled frames rendered by --visualize for the longest days";
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options { format: Format::Text, users: Users::Default, visualize: None, ticks: None, days: Vec::new() };
+    let mut frames_dir = None;
+    let mut backend = Backend::Ascii;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("Missing value for {}", name));
+        match arg.as_str() {
+            "--format" => {
+                options.format = match value("--format")?.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    other => return Err(format!("Unknown format {:?}", other)),
+                }
+            }
+            "--user" => options.users = Users::One(value("--user")?),
+            "--all-users" => options.users = Users::All,
+            "--visualize" => frames_dir = Some(PathBuf::from(value("--visualize")?)),
+            "--backend" => {
+                let name = value("--backend")?;
+                backend = name.parse().map_err(|_| format!("Unknown backend {:?}", name))?;
+            }
+            "--ticks" => {
+                let ticks = value("--ticks")?;
+                options.ticks = Some(ticks.parse().map_err(|_| format!("Invalid tick count {:?}", ticks))?);
+            }
+            "-h" | "--help" => return Err(String::new()),
+            day => options.days.push(day.parse().map_err(|_| format!("Invalid day {:?}", day))?),
+        }
+    }
+
+    options.visualize = frames_dir.map(|dir| (dir, backend));
+    Ok(options)
+}
+
+pub fn main(days: &[Day]) {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(error) => {
+            if !error.is_empty() { eprintln!("{}\n", error); }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let store = InputStore::new("inputs");
+    let all_users = matches!(options.users, Users::All);
+    let users = match &options.users {
+        Users::Default => vec![None],
+        Users::One(user) => vec![Some(user.clone())],
+        Users::All => store.users().into_iter().map(Some).collect(),
+    };
+    if users.is_empty() {
+        eprintln!("There are no user directories in inputs/");
+        std::process::exit(1);
+    }
+
+    // Panics are reported as errors of the part that caused them
+    install_panic_hook();
+
+    let mut reports = Vec::with_capacity(users.len());
+    for user in users.iter() {
+        let mut user_reports = Vec::new();
+        for day in days.iter().filter(|day| options.days.is_empty() || options.days.contains(&day.number)) {
+            let report = match store.read(user.as_deref(), day.number) {
+                Ok(input) => {
+                    if let (Some((dir, backend)), Some(visualize)) = (&options.visualize, day.visualize) {
+                        let frames_dir = user.iter().fold(dir.clone(), |dir, user| dir.join(user))
+                            .join(format!("day{:02}", day.number));
+                        let (result, _) = timed(|| {
+                            Frames::create(frames_dir, *backend)
+                                .and_then(|frames| visualize(&input, &mut frames.with_ticks(options.ticks)))
+                        });
+                        match result {
+                            Ok(Ok(())) => {}
+                            Ok(Err(error)) => eprintln!("Day {}: can't write frames: {}", day.number, error),
+                            Err(error) => eprintln!("Day {}: visualization failed: {}", day.number, error),
+                        }
+                    }
+                    (day.solve)(&input)
+                }
+                Err(error) => DayReport::failed(day.number, error),
+            };
+
+            match options.format {
+                Format::Json => print_json(&report, user.as_deref()),
+                Format::Text if all_users => user_reports.push(report),
+                Format::Text => print_text(&report),
+            }
+        }
+        reports.push(user_reports);
+    }
+
+    if options.format == Format::Text && all_users {
+        let users = users.into_iter().flatten().collect::<Vec<_>>();
+        print_table(&users, &reports);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Timings change from run to run, so they're replaced by a placeholder
+    fn records(report: &DayReport, user: Option<&str>) -> Vec<String> {
+        let time = Regex::new(r#"_time_ns":\d+"#).unwrap();
+        json_records(report, user).iter().map(|record| time.replace_all(&record.to_string(), r#"_time_ns":T"#).into_owned()).collect()
+    }
+
+    #[test]
+    fn json_record_per_part() {
+        let mut report = DayReport::new(5);
+        let input = report.parse(|| "a \"quoted\"\nanswer".to_string());
+        report.run(input.as_ref().map(|input| move || input.clone()));
+        report.run(Some(|| None::<u32>));
+        assert_eq!(records(&report, Some("alice")), [
+            r#"{"user":"alice","day":5,"part":1,"answer":"a \"quoted\"\nanswer","value":"a \"quoted\"\nanswer","parse_time_ns":T,"solve_time_ns":T,"error":null}"#,
+            r#"{"user":"alice","day":5,"part":2,"answer":"none","value":null,"parse_time_ns":T,"solve_time_ns":T,"error":null}"#,
+        ]);
+    }
+
+    #[test]
+    fn timed_reports_panics() {
+        let (result, _) = timed(|| -> u32 { panic!("Invalid input line {}", 3) });
+        assert_eq!(result, Err("Invalid input line 3".to_string()));
+        let (result, _) = timed(|| -> u32 { panic!("Invalid input") });
+        assert_eq!(result, Err("Invalid input".to_string()));
+        assert_eq!(timed(|| 5).0, Ok(5));
+    }
+
+    #[test]
+    fn json_records_of_failures() {
+        let mut report = DayReport::new(7);
+        let input = report.parse(|| -> u32 { panic!("Invalid input") });
+        report.run(input.map(|input| move || input));
+        assert_eq!(records(&report, None), [
+            r#"{"user":null,"day":7,"part":1,"answer":null,"value":null,"parse_time_ns":T,"solve_time_ns":null,"error":"The input generator failed"}"#,
+        ]);
+
+        let report = DayReport::failed(8, "Can't read inputs/day08.txt".to_string());
+        assert_eq!(records(&report, None), [
+            r#"{"user":null,"day":8,"part":null,"answer":null,"value":null,"parse_time_ns":null,"solve_time_ns":null,"error":"Can't read inputs/day08.txt"}"#,
+        ]);
+    }
+}
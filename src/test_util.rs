@@ -0,0 +1,44 @@
+// Helpers shared by the tests of several modules
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Linear congruential generator for reproducible random tests without a dependency
+pub struct Lcg(u32);
+
+impl Lcg {
+    pub fn new(seed: u32) -> Self { Lcg(seed) }
+
+    // Roughly uniform in `0..bound`, from the high bits since the low ones have short periods
+    pub fn below(&mut self, bound: u32) -> u32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (self.0 >> 16) % bound
+    }
+
+    pub fn range(&mut self, from: i64, to: i64) -> i64 {
+        from + self.below((to - from) as u32) as i64
+    }
+}
+
+// Empty directory under the system temporary directory, removed when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let unique = format!("aoc2018-{}-{}-{}", name, std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(unique);
+        fs::create_dir_all(&path).expect("Can't create the temporary directory");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}